@@ -1,6 +1,6 @@
 use anyhow::Result;
 
-use novel_api::{Client, Options, SfacgClient, WordCountRange};
+use novel_api::prelude::*;
 use tokio::fs;
 
 #[tokio::main]
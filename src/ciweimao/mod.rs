@@ -2,47 +2,71 @@ mod structure;
 mod utils;
 
 use std::{
-    io::{self, Cursor, Write},
+    collections::HashMap,
+    io::{self, Write},
     path::{Path, PathBuf},
     str::FromStr,
-    time::{SystemTime, UNIX_EPOCH},
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use async_trait::async_trait;
-use boring::{
-    hash::{self, MessageDigest},
-    sha,
-};
-use chrono::NaiveDateTime;
+use boring::hash::{self, MessageDigest};
+use chrono::{NaiveDate, NaiveDateTime};
 use hex_simd::AsciiCase;
-use image::{io::Reader, DynamicImage};
+use image::DynamicImage;
 use parking_lot::RwLock;
 use scraper::{Html, Selector};
 use serde_json::json;
-use tokio::sync::{mpsc, oneshot, OnceCell};
-use tracing::{error, info};
+use tokio::sync::{mpsc, oneshot, OnceCell, Semaphore};
+use tracing::{error, info, warn};
 use url::Url;
 use warp::{http::Response, Filter};
 
 use crate::{
-    Category, ChapterInfo, Client, ContentInfo, ContentInfos, Error, FindImageResult,
-    FindTextResult, HTTPClient, Identifier, NovelDB, NovelInfo, Options, Tag, UserInfo, VolumeInfo,
-    VolumeInfos, WordCountRange,
+    AuthorInfo, CacheSize, CacheVerdict, Category, CategoryGroup, ChapterInfo, ChapterPreview,
+    ChapterVersion, Client, ContentInfo, ContentInfos, EndpointMetrics, Error, FindTextResult,
+    HTTPClient, Identifier, ImageLimits, LoginRetry, MetricsCollector, NovelDB, NovelInfo,
+    NovelsPage, Options, PartialResult, ResponseExt, SignInStatus, Tag, TagLogic, TlsVersion,
+    Transport, UserInfo, VolumeInfo, VolumeInfos, WordCountRange,
 };
 use structure::*;
+use utils::Session;
 
 /// Ciweimao client, use it to access Apis
 #[must_use]
 pub struct CiweimaoClient {
     proxy: Option<Url>,
+    image_proxy: Option<Url>,
     no_proxy: bool,
     cert_path: Option<PathBuf>,
+    pinned_cert: Option<Vec<u8>>,
+    min_tls_version: Option<TlsVersion>,
+    pool_idle_timeout: Option<Duration>,
+    reqwest_client: Option<reqwest::Client>,
+    login_retry: LoginRetry,
+    host: Option<Url>,
+    stale_ok: bool,
+    image_limits: ImageLimits,
+    image_decodes: Semaphore,
+    write_behind_images: bool,
+    no_cache: bool,
+    image_cache_dir: Option<PathBuf>,
+    transport: Option<Arc<dyn Transport>>,
+    metrics: Option<Arc<MetricsCollector>>,
+    captcha_timeout: Duration,
+    persist_token: bool,
 
     client: OnceCell<HTTPClient>,
     client_rss: OnceCell<HTTPClient>,
 
     db: OnceCell<NovelDB>,
 
+    categories: OnceCell<Vec<Category>>,
+    category_groups: OnceCell<Vec<CategoryGroup>>,
+    tags: OnceCell<Vec<Tag>>,
+    taxonomy: OnceCell<Vec<(Category, Vec<Tag>)>>,
+
     account: RwLock<Option<String>>,
     login_token: RwLock<Option<String>>,
 }
@@ -51,9 +75,15 @@ pub struct CiweimaoClient {
 impl Client for CiweimaoClient {
     fn proxy(&mut self, proxy: Url) {
         self.proxy = Some(proxy);
+        self.no_proxy = false;
+    }
+
+    fn image_proxy(&mut self, proxy: Url) {
+        self.image_proxy = Some(proxy);
     }
 
     fn no_proxy(&mut self) {
+        self.proxy = None;
         self.no_proxy = true;
     }
 
@@ -64,11 +94,160 @@ impl Client for CiweimaoClient {
         self.cert_path = Some(cert_path.as_ref().to_path_buf());
     }
 
+    fn pin_cert(&mut self, der: &[u8]) {
+        self.pinned_cert = Some(der.to_vec());
+    }
+
+    fn min_tls_version(&mut self, version: TlsVersion) {
+        self.min_tls_version = Some(version);
+    }
+
+    fn login_retry(&mut self, policy: LoginRetry) {
+        self.login_retry = policy;
+    }
+
+    fn reqwest_client(&mut self, client: reqwest::Client) {
+        self.reqwest_client = Some(client);
+    }
+
+    fn pool_idle_timeout(&mut self, timeout: Duration) {
+        self.pool_idle_timeout = Some(timeout);
+    }
+
+    fn host(&mut self, host: Url) {
+        self.host = Some(host);
+    }
+
+    fn stale_ok(&mut self, flag: bool) {
+        self.stale_ok = flag;
+    }
+
+    fn enable_metrics(&mut self) {
+        self.metrics = Some(Arc::new(MetricsCollector::default()));
+    }
+
+    fn metrics(&self) -> HashMap<String, EndpointMetrics> {
+        self.metrics
+            .as_ref()
+            .map(|metrics| metrics.snapshot())
+            .unwrap_or_default()
+    }
+
+    fn image_limits(&mut self, limits: ImageLimits) {
+        self.image_limits = limits;
+    }
+
+    fn max_image_decodes(&mut self, count: usize) {
+        self.image_decodes = Semaphore::new(count.max(1));
+    }
+
+    fn write_behind_images(&mut self, flag: bool) {
+        self.write_behind_images = flag;
+    }
+
+    fn image_cache_fs(&mut self, dir: PathBuf) {
+        self.image_cache_dir = Some(dir);
+    }
+
+    fn no_cache(&mut self) {
+        self.no_cache = true;
+    }
+
+    fn site_name(&self) -> &'static str {
+        CiweimaoClient::APP_NAME
+    }
+
+    fn novel_url(&self, id: u32) -> Url {
+        Url::parse(&format!("https://www.ciweimao.com/book/{id}")).unwrap()
+    }
+
+    async fn flush(&self) -> Result<(), Error> {
+        match self.db.get() {
+            Some(db) => db.flush().await,
+            None => Ok(()),
+        }
+    }
+
+    async fn reset_connections(&self) -> Result<(), Error> {
+        if let Some(client) = self.client.get() {
+            client.reset().await?;
+        }
+
+        if let Some(client_rss) = self.client_rss.get() {
+            client_rss.reset().await?;
+        }
+
+        Ok(())
+    }
+
+    async fn cache_size(&self) -> Result<CacheSize, Error> {
+        crate::cache_size(self.db().await?, self.image_cache_dir.as_deref()).await
+    }
+
+    async fn export_cache(&self, out: &Path) -> Result<(), Error> {
+        crate::export_cache(CiweimaoClient::APP_NAME, self.db().await?, out).await
+    }
+
+    async fn import_cache(&self, archive: &Path, force: bool) -> Result<(), Error> {
+        if self.db.initialized() {
+            return Err(Error::NovelApi(
+                "The cache is in use and can not be overwritten".to_string(),
+            ));
+        }
+
+        crate::import_cache(CiweimaoClient::APP_NAME, archive, force).await
+    }
+
     async fn add_cookie(&self, cookie_str: &str, url: &Url) -> Result<(), Error> {
         Ok(self.client().await?.add_cookie(cookie_str, url)?)
     }
 
+    async fn export_session(&self) -> Result<String, Error> {
+        if !self.has_token() {
+            return Err(Error::NovelApi(
+                "There is no session to export, please log in first".to_string(),
+            ));
+        }
+
+        Ok(serde_json::to_string(&Session {
+            account: self.account(),
+            login_token: self.login_token(),
+        })?)
+    }
+
+    async fn import_session(&self, session: &str) -> Result<(), Error> {
+        let session: Session = serde_json::from_str(session)?;
+        self.save_token(session.account, session.login_token);
+
+        Ok(())
+    }
+
+    async fn login_saving_to_keyring<T, E>(&self, username: T, password: E) -> Result<(), Error>
+    where
+        T: AsRef<str> + Send + Sync,
+        E: AsRef<str> + Send + Sync,
+    {
+        self.login(username.as_ref(), password).await?;
+
+        crate::save_session_to_keyring(
+            CiweimaoClient::APP_NAME,
+            username.as_ref(),
+            self.export_session().await?,
+        )
+        .await
+    }
+
+    async fn login_from_keyring<T>(&self, username: T) -> Result<(), Error>
+    where
+        T: AsRef<str> + Send + Sync,
+    {
+        let session =
+            crate::load_session_from_keyring(CiweimaoClient::APP_NAME, username.as_ref()).await?;
+        self.import_session(&session).await
+    }
+
     async fn shutdown(&self) -> Result<(), Error> {
+        self.flush().await?;
         self.do_shutdown()
     }
 
@@ -77,23 +256,10 @@ impl Client for CiweimaoClient {
         T: AsRef<str> + Send + Sync,
         E: AsRef<str> + Send + Sync,
     {
-        let (account, login_token);
-
-        match self.verify_type(&username).await? {
-            VerifyType::None => {
-                info!("No verification required");
-                (account, login_token) = self.no_verification_login(username, password).await?;
-            }
-            VerifyType::Geetest => {
-                info!("Verify with Geetest");
-                (account, login_token) = self.geetest_login(username, password).await?;
-            }
-            VerifyType::VerifyCode => {
-                info!("Verify with SMS verification code");
-                (account, login_token) = self.sms_login(username, password).await?;
-            }
-        };
-
+        let (account, login_token) = self
+            .login_retry
+            .run(|| self.login_once(username.as_ref(), password.as_ref()))
+            .await?;
         self.save_token(account, login_token);
 
         Ok(())
@@ -121,9 +287,7 @@ impl Client for CiweimaoClient {
         check_response(response.code, response.tip)?;
 
         let user_info = UserInfo {
-            nickname: response
-                .data
-                .unwrap()
+            nickname: crate::check_data(response.data)?
                 .reader_info
                 .reader_name
                 .trim()
@@ -133,6 +297,58 @@ impl Client for CiweimaoClient {
         Ok(Some(user_info))
     }
 
+    async fn sign_in_status(&self) -> Result<SignInStatus, Error> {
+        if !self.has_token() {
+            return Err(Error::NotLoggedIn);
+        }
+
+        let response: SignRecordResponse = self
+            .post(
+                "/task/get_sign_record",
+                &SignRecordRequest {
+                    app_version: CiweimaoClient::APP_VERSION,
+                    device_token: CiweimaoClient::DEVICE_TOKEN,
+                    account: self.account(),
+                    login_token: self.login_token(),
+                },
+            )
+            .await?;
+        if response.code == CiweimaoClient::LOGIN_EXPIRED {
+            return Err(Error::NotLoggedIn);
+        }
+        check_response(response.code, response.tip)?;
+
+        let today = crate::today()?.format("%Y-%m-%d").to_string();
+        let is_signed_in = crate::check_data(response.data)?
+            .sign_record_list
+            .iter()
+            .any(|record| record.date.trim() == today && record.is_signed == "1");
+
+        Ok(SignInStatus { is_signed_in })
+    }
+
+    async fn sign_in(&self) -> Result<(), Error> {
+        if self.sign_in_status().await?.is_signed_in {
+            return Err(Error::AlreadySignedIn);
+        }
+
+        let response: SignInResponse = self
+            .post(
+                "/reader/get_task_bonus_with_sign_recommend",
+                &SignInRequest {
+                    app_version: CiweimaoClient::APP_VERSION,
+                    device_token: CiweimaoClient::DEVICE_TOKEN,
+                    account: self.account(),
+                    login_token: self.login_token(),
+                    task_type: 1,
+                },
+            )
+            .await?;
+        check_response(response.code, response.tip)?;
+
+        Ok(())
+    }
+
     async fn novel_info(&self, id: u32) -> Result<Option<NovelInfo>, Error> {
         let response: NovelInfoResponse = self
             .post(
@@ -149,13 +365,14 @@ impl Client for CiweimaoClient {
         if response.code == CiweimaoClient::NOT_FOUND {
             return Ok(None);
         }
-        check_response(response.code, response.tip)?;
+        check_novel_response(response.code, response.tip)?;
 
-        let data = response.data.unwrap().book_info;
+        let data = crate::check_data(response.data)?.book_info;
         let novel_info = NovelInfo {
             id,
             name: data.book_name.trim().to_string(),
             author_name: data.author_name.trim().to_string(),
+            author_id: CiweimaoClient::parse_number(data.author_id),
             cover_url: CiweimaoClient::parse_url(data.cover),
             introduction: CiweimaoClient::parse_introduction(data.description),
             word_count: CiweimaoClient::parse_number(data.total_word_count),
@@ -163,12 +380,47 @@ impl Client for CiweimaoClient {
             create_time: CiweimaoClient::parse_data_time(data.newtime),
             update_time: CiweimaoClient::parse_data_time(data.uptime),
             category: self.parse_category(data.category_index).await?,
+            all_tags: CiweimaoClient::parse_all_tags(&data.tag),
             tags: self.parse_tags(data.tag).await?,
+            view_count: CiweimaoClient::parse_number(data.total_click),
+            favorite_count: CiweimaoClient::parse_number(data.total_favor),
+            rating: None,
+            chapter_count: CiweimaoClient::parse_number(data.chapter_amount),
         };
 
         Ok(Some(novel_info))
     }
 
+    async fn author_info(&self, author_id: u32) -> Result<AuthorInfo, Error> {
+        let response: AuthorInfoResponse = self
+            .post(
+                "/reader/get_homepage_info",
+                &AuthorInfoRequest {
+                    app_version: CiweimaoClient::APP_VERSION,
+                    device_token: CiweimaoClient::DEVICE_TOKEN,
+                    account: self.account(),
+                    login_token: self.login_token(),
+                    reader_id: author_id,
+                },
+            )
+            .await?;
+        check_response(response.code, response.tip)?;
+
+        let data = crate::check_data(response.data)?;
+        let introduction = data.reader_info.signature.trim();
+
+        Ok(AuthorInfo {
+            name: data.reader_info.reader_name.trim().to_string(),
+            avatar_url: CiweimaoClient::parse_url(data.reader_info.avatar_url),
+            introduction: if introduction.is_empty() {
+                None
+            } else {
+                Some(introduction.to_string())
+            },
+            novel_count: data.book_list.map(|book_list| book_list.len() as u32),
+        })
+    }
+
     async fn volume_infos(&self, id: u32) -> Result<VolumeInfos, Error> {
         let response: VolumesResponse = self
             .post(
@@ -184,34 +436,16 @@ impl Client for CiweimaoClient {
             .await?;
         check_response(response.code, response.tip)?;
 
-        let mut volume_infos = VolumeInfos::new();
-        for item in response.data.unwrap().chapter_list {
-            let mut volume_info = VolumeInfo {
-                title: item.division_name.trim().to_string(),
-                chapter_infos: Vec::new(),
-            };
-
-            for chapter in item.chapter_list {
-                let chapter_info = ChapterInfo {
-                    identifier: Identifier::Id(chapter.chapter_id.parse::<u32>()?),
-                    title: chapter.chapter_title.trim().to_string(),
-                    word_count: CiweimaoClient::parse_number(chapter.word_count),
-                    update_time: CiweimaoClient::parse_data_time(chapter.mtime),
-                    is_vip: None,
-                    is_accessible: CiweimaoClient::parse_bool(chapter.auth_access),
-                    is_valid: CiweimaoClient::parse_bool(chapter.is_valid),
-                };
-
-                volume_info.chapter_infos.push(chapter_info);
-            }
-
-            volume_infos.push(volume_info);
-        }
-
-        Ok(volume_infos)
+        CiweimaoClient::parse_volume_infos(crate::check_data(response.data)?)
     }
 
     async fn content_infos(&self, info: &ChapterInfo) -> Result<ContentInfos, Error> {
+        if self.no_cache {
+            return Ok(CiweimaoClient::parse_content(
+                self.fetch_content(info).await?,
+            ));
+        }
+
         let content;
 
         match self.db().await?.find_text(info).await? {
@@ -219,72 +453,193 @@ impl Client for CiweimaoClient {
                 content = str;
             }
             other => {
-                let identifier = info.identifier.to_string();
-
-                let cmd = self.chapter_cmd(&identifier).await?;
-                let aes_key = sha::sha256(cmd.as_bytes());
-
-                let response: ChapsResponse = self
-                    .post(
-                        "/chapter/get_cpt_ifm",
-                        &ChapsRequest {
-                            app_version: CiweimaoClient::APP_VERSION,
-                            device_token: CiweimaoClient::DEVICE_TOKEN,
-                            account: self.account(),
-                            login_token: self.login_token(),
-                            chapter_id: identifier,
-                            chapter_command: cmd,
-                        },
-                    )
-                    .await?;
-                check_response(response.code, response.tip)?;
-
-                let conetent = CiweimaoClient::aes_256_cbc_base64_decrypt(
-                    aes_key,
-                    response.data.unwrap().chapter_info.txt_content,
-                )?;
-                content = simdutf8::basic::from_utf8(&conetent)?.to_string();
+                match self.fetch_content(info).await {
+                    Ok(str) => content = str,
+                    Err(error)
+                        if self.stale_ok
+                            && matches!(other, FindTextResult::Outdate)
+                            && error.is_retryable() =>
+                    {
+                        match self.db().await?.find_stale_text(info).await? {
+                            Some(str) => {
+                                warn!(
+                                    "Failed to download chapter: `{}`, use the outdated cache, {error}",
+                                    info.title
+                                );
+                                return Ok(CiweimaoClient::parse_content(str));
+                            }
+                            None => return Err(error),
+                        }
+                    }
+                    Err(error) => return Err(error),
+                }
 
                 match other {
                     FindTextResult::None => self.db().await?.insert_text(info, &content).await?,
-                    FindTextResult::Outdate => self.db().await?.update_text(info, &content).await?,
+                    FindTextResult::Outdate => {
+                        let db = self.db().await?;
+                        db.keep_version(info, &content).await?;
+                        db.update_text(info, &content).await?;
+                    }
                     FindTextResult::Ok(_) => (),
                 }
             }
         }
 
-        let mut content_infos = ContentInfos::new();
-        for line in content
-            .lines()
-            .map(|line| line.trim())
-            .filter(|line| !line.is_empty())
-        {
-            if line.starts_with("<img") {
-                if let Some(url) = CiweimaoClient::parse_image_url(line) {
-                    content_infos.push(ContentInfo::Image(url));
+        Ok(CiweimaoClient::parse_content(content))
+    }
+
+    async fn chapter_preview(&self, info: &ChapterInfo) -> Result<Option<ChapterPreview>, Error> {
+        if info.can_download() {
+            match self.content_infos(info).await {
+                Ok(content_infos) => {
+                    return Ok(Some(ChapterPreview {
+                        content_infos,
+                        is_preview: false,
+                    }))
                 }
-            } else {
-                content_infos.push(ContentInfo::Text(line.to_string()));
+                Err(Error::ChapterLocked(_)) => (),
+                Err(error) => return Err(error),
             }
         }
 
-        Ok(content_infos)
+        let cached = if self.no_cache {
+            None
+        } else {
+            self.db().await?.find_preview(info).await?
+        };
+
+        let content = match cached {
+            Some(str) => str,
+            None => {
+                let str = self.fetch_raw_content(info).await?;
+                if str.trim().is_empty() {
+                    return Ok(None);
+                }
+
+                if !self.no_cache {
+                    self.db().await?.save_preview(info, &str).await?;
+                }
+                str
+            }
+        };
+
+        Ok(Some(ChapterPreview {
+            content_infos: CiweimaoClient::parse_content(content),
+            is_preview: true,
+        }))
+    }
+
+    /// ciweimao has no endpoint for the history of a chapter, the earlier versions are the texts
+    /// kept in the cache when an updated chapter replaced them
+    async fn chapter_versions(&self, info: &ChapterInfo) -> Result<Vec<ChapterVersion>, Error> {
+        let mut versions = vec![ChapterVersion {
+            id: ChapterVersion::CURRENT,
+            update_time: info.update_time,
+        }];
+
+        if !self.no_cache {
+            versions.extend(
+                self.db()
+                    .await?
+                    .find_versions(info)
+                    .await?
+                    .into_iter()
+                    .map(|(id, update_time)| ChapterVersion { id, update_time }),
+            );
+        }
+
+        Ok(versions)
+    }
+
+    async fn content_infos_version(
+        &self,
+        info: &ChapterInfo,
+        version_id: u32,
+    ) -> Result<ContentInfos, Error> {
+        if version_id == ChapterVersion::CURRENT {
+            return self.content_infos(info).await;
+        }
+
+        let text = if self.no_cache {
+            None
+        } else {
+            self.db().await?.find_version(info, version_id).await?
+        };
+
+        match text {
+            Some(text) => Ok(CiweimaoClient::parse_content(text)),
+            None => Err(Error::NovelApi(format!(
+                "The version `{version_id}` of the chapter is not available: `{}`",
+                info.title
+            ))),
+        }
+    }
+
+    async fn is_content_cached(&self, info: &ChapterInfo) -> Result<bool, Error> {
+        Ok(matches!(
+            self.db().await?.find_text(info).await?,
+            FindTextResult::Ok(_)
+        ))
+    }
+
+    async fn verify_cached_chapter(&self, info: &ChapterInfo) -> Result<CacheVerdict, Error> {
+        match self.db().await?.find_text(info).await? {
+            FindTextResult::Ok(str) => Ok(crate::verify_word_count(
+                info,
+                &CiweimaoClient::parse_content(str),
+            )),
+            FindTextResult::None | FindTextResult::Outdate => Ok(CacheVerdict::NotCached),
+        }
+    }
+
+    async fn clear_chapters_cache(&self, identifiers: &[Identifier]) -> Result<u64, Error> {
+        self.db().await?.delete_texts(identifiers).await
+    }
+
+    async fn cache_content(&self, info: &ChapterInfo, text: &str) -> Result<(), Error> {
+        self.db().await?.save_text(info, text).await
+    }
+
+    async fn cache_image(&self, url: &Url, bytes: &[u8]) -> Result<(), Error> {
+        match &self.image_cache_dir {
+            Some(dir) => crate::save_image_file(dir, url, bytes).await,
+            None => self.db().await?.save_image(url, bytes).await,
+        }
     }
 
     async fn image(&self, url: &Url) -> Result<DynamicImage, Error> {
-        match self.db().await?.find_image(url).await? {
-            FindImageResult::Ok(image) => Ok(image),
-            FindImageResult::None => {
-                let response = self.get_rss(url).await?;
-                let bytes = response.bytes().await?;
+        let bytes = self.image_bytes(url).await?;
+        crate::decode_image_blocking(bytes, self.image_limits, &self.image_decodes).await
+    }
+
+    async fn image_bytes(&self, url: &Url) -> Result<Vec<u8>, Error> {
+        if self.no_cache {
+            return self.fetch_image_bytes(url).await;
+        }
 
-                let image = Reader::new(Cursor::new(&bytes))
-                    .with_guessed_format()?
-                    .decode()?;
+        let cached = match &self.image_cache_dir {
+            Some(dir) => crate::find_image_file(dir, url).await?,
+            None => self.db().await?.find_image_bytes(url).await?,
+        };
 
-                self.db().await?.insert_image(url, bytes).await?;
+        match cached {
+            Some(bytes) => Ok(bytes),
+            None => {
+                let bytes = self.fetch_image_bytes(url).await?;
+
+                if let Some(dir) = &self.image_cache_dir {
+                    crate::save_image_file(dir, url, &bytes).await?;
+                } else if self.write_behind_images {
+                    self.db()
+                        .await?
+                        .insert_image_behind(url, bytes.clone())
+                        .await;
+                } else {
+                    self.db().await?.insert_image(url, &bytes).await?;
+                }
 
-                Ok(image)
+                Ok(bytes)
             }
         }
     }
@@ -293,73 +648,53 @@ impl Client for CiweimaoClient {
     where
         T: AsRef<str> + Send + Sync,
     {
-        let response: SearchResponse = self
-            .post(
-                "/bookcity/get_filter_search_book_list",
-                &SearchRequest {
-                    app_version: CiweimaoClient::APP_VERSION,
-                    device_token: CiweimaoClient::DEVICE_TOKEN,
-                    account: self.account(),
-                    login_token: self.login_token(),
-                    key: text.as_ref().to_string(),
-                    count: size,
-                    page,
-                },
-            )
-            .await?;
-        check_response(response.code, response.tip)?;
-
-        let mut result = Vec::new();
-        if response.data.is_some() {
-            for novel_info in response.data.unwrap().book_list {
-                result.push(novel_info.book_id.parse::<u32>()?);
-            }
-        }
+        self.search(text, None, page, size).await
+    }
 
-        Ok(result)
+    async fn search_infos_in<T>(
+        &self,
+        text: T,
+        category: &Category,
+        page: u16,
+        size: u16,
+        _concurrency: usize,
+    ) -> Result<Vec<u32>, Error>
+    where
+        T: AsRef<str> + Send + Sync,
+    {
+        self.search(text, category.id, page, size).await
     }
 
     async fn bookshelf_infos(&self) -> Result<Vec<u32>, Error> {
-        let shelf_ids = self.shelf_list().await?;
-        let mut result = Vec::new();
+        let mut result = self.bookshelf_infos_partial().await?;
 
-        for shelf_id in shelf_ids {
-            let response: FavoritesResponse = self
-                .post(
-                    "/bookshelf/get_shelf_book_list_new",
-                    &FavoritesRequest {
-                        app_version: CiweimaoClient::APP_VERSION,
-                        device_token: CiweimaoClient::DEVICE_TOKEN,
-                        account: self.account(),
-                        login_token: self.login_token(),
-                        shelf_id,
-                    },
-                )
-                .await?;
-            check_response(response.code, response.tip)?;
-
-            if response.data.is_some() {
-                for novel_info in response.data.unwrap().book_list {
-                    result.push(novel_info.book_info.book_id.parse::<u32>()?);
-                }
-            }
+        if result.value.is_empty() && !result.errors.is_empty() {
+            return Err(result.errors.swap_remove(0));
         }
 
-        result.sort_unstable();
-        result.dedup();
-
-        Ok(result)
+        Ok(result.value)
     }
 
     async fn categories(&self) -> Result<&Vec<Category>, Error> {
-        static CATEGORIES: OnceCell<Vec<Category>> = OnceCell::const_new();
+        self.categories
+            .get_or_try_init(|| async {
+                let mut result = Vec::new();
+                for category_group in self.categories_grouped().await? {
+                    result.extend(category_group.categories.iter().cloned());
+                }
 
-        CATEGORIES
+                Ok(result)
+            })
+            .await
+    }
+
+    async fn tags(&self) -> Result<&Vec<Tag>, Error> {
+        self.tags
             .get_or_try_init(|| async {
-                let response: CategoryResponse = self
+                let response: TagResponse = self
                     .post(
-                        "/meta/get_meta_data",
-                        &CategoryRequest {
+                        "/book/get_official_tag_list",
+                        &TagRequest {
                             app_version: CiweimaoClient::APP_VERSION,
                             device_token: CiweimaoClient::DEVICE_TOKEN,
                             account: self.account(),
@@ -370,13 +705,11 @@ impl Client for CiweimaoClient {
                 check_response(response.code, response.tip)?;
 
                 let mut result = Vec::new();
-                for category in response.data.unwrap().category_list {
-                    for category_detail in category.category_detail {
-                        result.push(Category {
-                            id: CiweimaoClient::parse_number(category_detail.category_index),
-                            name: category_detail.category_name.trim().to_string(),
-                        });
-                    }
+                for tag in crate::check_data(response.data)?.official_tag_list {
+                    result.push(Tag {
+                        id: None,
+                        name: tag.tag_name.trim().to_string(),
+                    });
                 }
 
                 Ok(result)
@@ -384,42 +717,33 @@ impl Client for CiweimaoClient {
             .await
     }
 
-    async fn tags(&self) -> Result<&Vec<Tag>, Error> {
-        static TAGS: OnceCell<Vec<Tag>> = OnceCell::const_new();
-
-        TAGS.get_or_try_init(|| async {
-            let response: TagResponse = self
-                .post(
-                    "/book/get_official_tag_list",
-                    &TagRequest {
-                        app_version: CiweimaoClient::APP_VERSION,
-                        device_token: CiweimaoClient::DEVICE_TOKEN,
-                        account: self.account(),
-                        login_token: self.login_token(),
-                    },
-                )
-                .await?;
-            check_response(response.code, response.tip)?;
-
-            let mut result = Vec::new();
-            for tag in response.data.unwrap().official_tag_list {
-                result.push(Tag {
-                    id: None,
-                    name: tag.tag_name.trim().to_string(),
-                });
-            }
-
-            Ok(result)
-        })
-        .await
+    async fn browse_taxonomy(&self) -> Result<&Vec<(Category, Vec<Tag>)>, Error> {
+        self.taxonomy
+            .get_or_try_init(|| crate::fetch_taxonomy(self))
+            .await
     }
 
-    async fn novels(&self, option: &Options, page: u16, size: u16) -> Result<Vec<u32>, Error> {
+    async fn novels_page(
+        &self,
+        option: &Options,
+        page: u16,
+        size: u16,
+    ) -> Result<NovelsPage, Error> {
+        if let Some(sort) = &option.sort {
+            return Err(Error::NovelApi(format!(
+                "The sort `{sort}` is not supported, ciweimao does not support `Options::sort`, \
+                 the only supported value is `None`, which lists the novels in the default order of the site"
+            )));
+        }
+
         let mut category_id = 0;
         if option.category.is_some() {
             category_id = option.category.as_ref().unwrap().id.unwrap();
         }
 
+        // Every tag with `filter: "1"` must be present in the novels returned by ciweimao
+        option.check_tag_logic(TagLogic::And)?;
+
         let json_obj = |tag_name| {
             json!({
                 "tag": tag_name,
@@ -503,7 +827,7 @@ impl Client for CiweimaoClient {
                     device_token: CiweimaoClient::DEVICE_TOKEN,
                     account: self.account(),
                     login_token: self.login_token(),
-                    count: size,
+                    count: crate::page_size(size, CiweimaoClient::MAX_PAGE_SIZE),
                     page,
                     category_index: category_id,
                     order: "week_click",
@@ -517,14 +841,83 @@ impl Client for CiweimaoClient {
             .await?;
         check_response(response.code, response.tip)?;
 
-        let mut result = Vec::new();
-        if response.data.is_some() {
-            for novel_info in response.data.unwrap().book_list {
-                result.push(novel_info.book_id.parse::<u32>()?);
+        let mut ids = Vec::new();
+        let mut total = None;
+        if response.data.is_some() {
+            let data = response.data.unwrap();
+
+            for novel_info in data.book_list {
+                ids.push(novel_info.book_id.parse::<u32>()?);
+            }
+            total = data.total.and_then(CiweimaoClient::parse_number);
+        }
+
+        Ok(NovelsPage { ids, total })
+    }
+}
+
+impl CiweimaoClient {
+    /// Get the favorite novel of the logged-in user and return the novel id
+    ///
+    /// A shelf that fails to be fetched does not affect other shelves, its error is returned along with the novel ids
+    pub async fn bookshelf_infos_partial(&self) -> Result<PartialResult<Vec<u32>>, Error> {
+        let shelf_ids = self.shelf_list().await?;
+
+        let mut value = Vec::new();
+        let mut errors = Vec::new();
+
+        for shelf_id in shelf_ids {
+            match self.shelf_book_list(shelf_id).await {
+                Ok(ids) => value.extend(ids),
+                Err(error) => {
+                    error!("Failed to get the shelf `{shelf_id}`: {error}");
+                    errors.push(error);
+                }
             }
         }
 
-        Ok(result)
+        value.sort_unstable();
+        value.dedup();
+
+        Ok(PartialResult { value, errors })
+    }
+
+    /// Get all categories, grouped by their parent category
+    pub async fn categories_grouped(&self) -> Result<&Vec<CategoryGroup>, Error> {
+        self.category_groups
+            .get_or_try_init(|| async {
+                let response: CategoryResponse = self
+                    .post(
+                        "/meta/get_meta_data",
+                        &CategoryRequest {
+                            app_version: CiweimaoClient::APP_VERSION,
+                            device_token: CiweimaoClient::DEVICE_TOKEN,
+                            account: self.account(),
+                            login_token: self.login_token(),
+                        },
+                    )
+                    .await?;
+                check_response(response.code, response.tip)?;
+
+                let mut result = Vec::new();
+                for category in crate::check_data(response.data)?.category_list {
+                    let mut categories = Vec::new();
+                    for category_detail in category.category_detail {
+                        categories.push(Category {
+                            id: CiweimaoClient::parse_number(category_detail.category_index),
+                            name: category_detail.category_name.trim().to_string(),
+                        });
+                    }
+
+                    result.push(CategoryGroup {
+                        name: category.category_name.trim().to_string(),
+                        categories,
+                    });
+                }
+
+                Ok(result)
+            })
+            .await
     }
 }
 
@@ -536,6 +929,23 @@ enum VerifyType {
 }
 
 impl CiweimaoClient {
+    async fn login_once(&self, username: &str, password: &str) -> Result<(String, String), Error> {
+        match self.verify_type(username).await? {
+            VerifyType::None => {
+                info!("No verification required");
+                self.no_verification_login(username, password).await
+            }
+            VerifyType::Geetest => {
+                info!("Verify with Geetest");
+                self.geetest_login(username, password).await
+            }
+            VerifyType::VerifyCode => {
+                info!("Verify with SMS verification code");
+                self.sms_login(username, password).await
+            }
+        }
+    }
+
     async fn verify_type<T>(&self, username: T) -> Result<VerifyType, Error>
     where
         T: AsRef<str>,
@@ -552,7 +962,7 @@ impl CiweimaoClient {
             .await?;
         check_response(response.code, response.tip)?;
 
-        let data = response.data.unwrap();
+        let data = crate::check_data(response.data)?;
         if data.need_use_geetest == "0" {
             Ok(VerifyType::None)
         } else if data.need_use_geetest == "1" {
@@ -584,9 +994,9 @@ impl CiweimaoClient {
                 },
             )
             .await?;
-        check_response(response.code, response.tip)?;
+        check_login_response(response.code, response.tip)?;
 
-        let data = response.data.unwrap();
+        let data = crate::check_data(response.data)?;
         Ok((data.reader_info.account, data.login_token))
     }
 
@@ -598,7 +1008,7 @@ impl CiweimaoClient {
         let info = self.geetest_info(&username).await?;
         let geetest_challenge = info.challenge.clone();
 
-        let validate = CiweimaoClient::run_server(info).await?;
+        let validate = CiweimaoClient::run_server(info, self.captcha_timeout).await?;
 
         let response: LoginResponse = self
             .post(
@@ -614,9 +1024,9 @@ impl CiweimaoClient {
                 },
             )
             .await?;
-        check_response(response.code, response.tip)?;
+        check_login_response(response.code, response.tip)?;
 
-        let data = response.data.unwrap();
+        let data = crate::check_data(response.data)?;
         Ok((data.reader_info.account, data.login_token))
     }
 
@@ -635,7 +1045,7 @@ impl CiweimaoClient {
                 },
             )
             .await?
-            .json::<GeetestInfoResponse>()
+            .json_decoded::<GeetestInfoResponse>()
             .await?;
 
         if response.success != 1 {
@@ -647,7 +1057,7 @@ impl CiweimaoClient {
         Ok(response)
     }
 
-    async fn run_server(info: GeetestInfoResponse) -> Result<String, Error> {
+    async fn run_server(info: GeetestInfoResponse, timeout: Duration) -> Result<String, Error> {
         #[cfg(target_os = "windows")]
         macro_rules! PATH_SEPARATOR {
             () => {
@@ -702,12 +1112,20 @@ impl CiweimaoClient {
         );
         tokio::task::spawn(server);
 
-        opener::open_browser(format!("http://{}:{}/captcha", addr.ip(), addr.port()))?;
+        if let Err(error) =
+            opener::open_browser(format!("http://{}:{}/captcha", addr.ip(), addr.port()))
+        {
+            let _ = stop_tx.send(());
+            return Err(error.into());
+        }
 
-        let validate = rx.recv().await.unwrap();
-        stop_tx.send(()).unwrap();
+        let validate = tokio::time::timeout(timeout, rx.recv()).await;
+        let _ = stop_tx.send(());
 
-        Ok(validate)
+        match validate {
+            Ok(Some(validate)) => Ok(validate),
+            _ => Err(Error::NovelApi("captcha timed out".to_string())),
+        }
     }
 
     async fn sms_login<T, E>(&self, username: T, password: E) -> Result<(String, String), Error>
@@ -758,17 +1176,54 @@ impl CiweimaoClient {
                     device_token: CiweimaoClient::DEVICE_TOKEN,
                     login_name: username.as_ref().to_string(),
                     passwd: password.as_ref().to_string(),
-                    to_code: response.data.unwrap().to_code,
+                    to_code: crate::check_data(response.data)?.to_code,
                     ver_code: ver_code.trim().to_string(),
                 },
             )
             .await?;
-        check_response(response.code, response.tip)?;
+        check_login_response(response.code, response.tip)?;
 
-        let data = response.data.unwrap();
+        let data = crate::check_data(response.data)?;
         Ok((data.reader_info.account, data.login_token))
     }
 
+    async fn search<T>(
+        &self,
+        text: T,
+        category_index: Option<u16>,
+        page: u16,
+        size: u16,
+    ) -> Result<Vec<u32>, Error>
+    where
+        T: AsRef<str> + Send + Sync,
+    {
+        let response: SearchResponse = self
+            .post(
+                "/bookcity/get_filter_search_book_list",
+                &SearchRequest {
+                    app_version: CiweimaoClient::APP_VERSION,
+                    device_token: CiweimaoClient::DEVICE_TOKEN,
+                    account: self.account(),
+                    login_token: self.login_token(),
+                    key: text.as_ref().to_string(),
+                    count: crate::page_size(size, CiweimaoClient::MAX_PAGE_SIZE),
+                    page,
+                    category_index,
+                },
+            )
+            .await?;
+        check_response(response.code, response.tip)?;
+
+        let mut result = Vec::new();
+        if response.data.is_some() {
+            for novel_info in response.data.unwrap().book_list {
+                result.push(novel_info.book_id.parse::<u32>()?);
+            }
+        }
+
+        Ok(result)
+    }
+
     // TODO use /chapter/get_chapter_cmd_s
     async fn chapter_cmd<T>(&self, identifier: T) -> Result<String, Error>
     where
@@ -788,7 +1243,32 @@ impl CiweimaoClient {
             .await?;
         check_response(response.code, response.tip)?;
 
-        Ok(response.data.unwrap().command)
+        Ok(crate::check_data(response.data)?.command)
+    }
+
+    async fn shelf_book_list(&self, shelf_id: u32) -> Result<Vec<u32>, Error> {
+        let response: FavoritesResponse = self
+            .post(
+                "/bookshelf/get_shelf_book_list_new",
+                &FavoritesRequest {
+                    app_version: CiweimaoClient::APP_VERSION,
+                    device_token: CiweimaoClient::DEVICE_TOKEN,
+                    account: self.account(),
+                    login_token: self.login_token(),
+                    shelf_id,
+                },
+            )
+            .await?;
+        check_response(response.code, response.tip)?;
+
+        let mut result = Vec::new();
+        if let Some(data) = response.data {
+            for novel_info in data.book_list {
+                result.push(novel_info.book_info.book_id.parse::<u32>()?);
+            }
+        }
+
+        Ok(result)
     }
 
     // NOTE book_limit = 50
@@ -816,17 +1296,57 @@ impl CiweimaoClient {
         Ok(result)
     }
 
+    fn parse_volume_infos(data: VolumesData) -> Result<VolumeInfos, Error> {
+        let mut volume_infos = VolumeInfos::new();
+        for item in data.chapter_list {
+            let mut volume_info = VolumeInfo {
+                title: item.division_name.trim().to_string(),
+                description: CiweimaoClient::parse_description(item.description),
+                cover_url: CiweimaoClient::parse_url(item.cover.trim()),
+                chapter_infos: Vec::new(),
+            };
+
+            for chapter in item.chapter_list {
+                let chapter_info = ChapterInfo {
+                    identifier: Identifier::Id(chapter.chapter_id.parse::<u32>()?),
+                    title: chapter.chapter_title.trim().to_string(),
+                    word_count: chapter.word_count.and_then(CiweimaoClient::parse_number),
+                    update_time: chapter.mtime.and_then(CiweimaoClient::parse_data_time),
+                    is_vip: chapter.is_paid.and_then(CiweimaoClient::parse_bool),
+                    is_accessible: chapter.auth_access.and_then(CiweimaoClient::parse_bool),
+                    is_valid: chapter.is_valid.and_then(CiweimaoClient::parse_bool),
+                };
+
+                volume_info.chapter_infos.push(chapter_info);
+            }
+
+            volume_infos.push(volume_info);
+        }
+
+        Ok(volume_infos)
+    }
+
+    /// Parse a time in the format `YYYY-MM-DD HH:MM:SS`, `YYYY-MM-DD` or epoch seconds,
+    /// the times of ciweimao are in China Standard Time (UTC+8), so epoch seconds are converted to it
     fn parse_data_time<T>(str: T) -> Option<NaiveDateTime>
     where
         T: AsRef<str>,
     {
-        let str = str.as_ref();
+        let str = str.as_ref().trim();
         if str.is_empty() {
             return None;
         }
 
-        match NaiveDateTime::from_str(&str.replace(' ', "T")) {
-            Ok(data_time) => Some(data_time),
+        if let Ok(data_time) = NaiveDateTime::from_str(&str.replace(' ', "T")) {
+            return Some(data_time);
+        }
+
+        if let Ok(date) = NaiveDate::from_str(str) {
+            return date.and_hms_opt(0, 0, 0);
+        }
+
+        match str.parse::<u32>() {
+            Ok(timestamp) => crate::cst_from_timestamp(timestamp.into()),
             Err(error) => {
                 error!("NaiveDateTime parse failed: {error}, content: {str}");
                 None
@@ -887,6 +1407,70 @@ impl CiweimaoClient {
         }
     }
 
+    async fn fetch_image_bytes(&self, url: &Url) -> Result<Vec<u8>, Error> {
+        let bytes = self.get_rss(url).await?.bytes().await?;
+        image::guess_format(&bytes)?;
+
+        Ok(bytes.to_vec())
+    }
+
+    async fn fetch_content(&self, info: &ChapterInfo) -> Result<String, Error> {
+        let content = self.fetch_raw_content(info).await?;
+        crate::check_content(info, &content)?;
+
+        Ok(content)
+    }
+
+    /// Download the content without checking whether it is the preview of a locked chapter
+    async fn fetch_raw_content(&self, info: &ChapterInfo) -> Result<String, Error> {
+        let identifier = info.identifier.to_string();
+
+        let cmd = self.chapter_cmd(&identifier).await?;
+        let aes_key = CiweimaoClient::chapter_key(&cmd);
+
+        let response: ChapsResponse = self
+            .post(
+                "/chapter/get_cpt_ifm",
+                &ChapsRequest {
+                    app_version: CiweimaoClient::APP_VERSION,
+                    device_token: CiweimaoClient::DEVICE_TOKEN,
+                    account: self.account(),
+                    login_token: self.login_token(),
+                    chapter_id: identifier,
+                    chapter_command: cmd,
+                },
+            )
+            .await?;
+        check_response(response.code, response.tip)?;
+
+        let content = CiweimaoClient::aes_256_cbc_base64_decrypt(
+            aes_key,
+            crate::check_data(response.data)?.chapter_info.txt_content,
+        )?;
+        Ok(simdutf8::basic::from_utf8(&content)?.to_string())
+    }
+
+    fn parse_content(content: String) -> ContentInfos {
+        let content = crate::normalize_text(content);
+
+        let mut content_infos = ContentInfos::new();
+        for line in content
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+        {
+            if line.starts_with("<img") {
+                if let Some(url) = CiweimaoClient::parse_image_url(line) {
+                    content_infos.push(ContentInfo::Image(url));
+                }
+            } else {
+                content_infos.push(ContentInfo::Text(line.to_string()));
+            }
+        }
+
+        content_infos
+    }
+
     async fn parse_tags<T>(&self, str: T) -> Result<Option<Vec<Tag>>, Error>
     where
         T: AsRef<str>,
@@ -916,6 +1500,24 @@ impl CiweimaoClient {
         }
     }
 
+    fn parse_all_tags(str: &str) -> Option<Vec<Tag>> {
+        let result = str
+            .split(',')
+            .map(|tag| tag.trim())
+            .filter(|tag| !tag.is_empty())
+            .map(|tag| Tag {
+                id: None,
+                name: tag.to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        if result.is_empty() {
+            None
+        } else {
+            Some(result)
+        }
+    }
+
     async fn parse_category<T>(&self, str: T) -> Result<Option<Category>, Error>
     where
         T: AsRef<str>,
@@ -964,6 +1566,19 @@ impl CiweimaoClient {
         }
     }
 
+    fn parse_description<T>(str: T) -> Option<String>
+    where
+        T: AsRef<str>,
+    {
+        let str = str.as_ref().trim();
+
+        if str.is_empty() {
+            None
+        } else {
+            Some(str.to_string())
+        }
+    }
+
     fn parse_image_url<T>(str: T) -> Option<Url>
     where
         T: AsRef<str>,
@@ -993,3 +1608,257 @@ impl CiweimaoClient {
         CiweimaoClient::parse_url(url.trim())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use http::StatusCode;
+    use pretty_assertions::assert_eq;
+
+    use crate::MockTransport;
+
+    fn encrypt(json: &str) -> Vec<u8> {
+        CiweimaoClient::aes_256_cbc_base64_encrypt(CiweimaoClient::get_default_key(), json).unwrap()
+    }
+
+    async fn mock_novel_info(json: &str) -> Result<Option<NovelInfo>, Error> {
+        let transport = MockTransport::default().response(
+            "/book/get_info_by_id",
+            StatusCode::OK,
+            encrypt(json),
+        );
+
+        let mut client = CiweimaoClient::new().await?;
+        client.transport = Some(Arc::new(transport));
+        // Do not save the fake token to the config file
+        client.persist_token(false);
+        client.save_token(String::from("account"), String::from("login_token"));
+
+        client.novel_info(1).await
+    }
+
+    #[tokio::test]
+    async fn session() -> Result<(), Error> {
+        let mut client = CiweimaoClient::new().await?;
+        // Do not save the fake token to the config file
+        client.persist_token(false);
+        client.save_token(String::from("account"), String::from("login_token"));
+        let session = client.export_session().await?;
+
+        let mut client = CiweimaoClient::new().await?;
+        client.persist_token(false);
+        client.import_session(&session).await?;
+        assert_eq!(client.account(), "account");
+        assert_eq!(client.login_token(), "login_token");
+
+        assert!(client.import_session("invalid").await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn novel_info() -> Result<(), Error> {
+        let novel_info = mock_novel_info(
+            r#"{
+                "code": "100000",
+                "data": {
+                    "book_info": {
+                        "book_name": " 测试小说 ",
+                        "author_name": "作者",
+                        "cover": "",
+                        "description": "第一行\n第二行",
+                        "total_word_count": "1000",
+                        "up_status": "1",
+                        "newtime": "2020-07-08 15:25:15",
+                        "uptime": "2020-07-08 15:25:17",
+                        "category_index": "",
+                        "tag": "",
+                        "total_click": "100",
+                        "total_favor": "10",
+                        "chapter_amount": "320"
+                    }
+                }
+            }"#,
+        )
+        .await?
+        .unwrap();
+
+        assert_eq!(novel_info.name, "测试小说");
+        assert_eq!(novel_info.word_count, Some(1000));
+        assert_eq!(novel_info.is_finished, Some(true));
+        assert_eq!(novel_info.view_count, Some(100));
+        assert_eq!(novel_info.favorite_count, Some(10));
+        assert_eq!(novel_info.chapter_count, Some(320));
+
+        assert!(
+            mock_novel_info(r#"{"code": "320001", "tip": "书籍不存在"}"#)
+                .await?
+                .is_none()
+        );
+
+        assert!(matches!(
+            mock_novel_info(r#"{"code": "320002", "tip": "该书已下架"}"#).await,
+            Err(Error::NovelUnavailable { reason }) if reason == "该书已下架"
+        ));
+
+        assert!(matches!(
+            mock_novel_info(r#"{"code": "320003", "tip": "书籍审核中"}"#).await,
+            Err(Error::NovelUnavailable { reason }) if reason == "书籍审核中"
+        ));
+
+        assert!(matches!(
+            mock_novel_info(r#"{"code": "320004"}"#).await,
+            Err(Error::NovelUnavailable { reason }) if reason.contains("320004")
+        ));
+
+        assert!(matches!(
+            mock_novel_info(r#"{"code": "240001", "tip": "该操作审核中，请稍后重试"}"#).await,
+            Err(Error::NovelApi(_))
+        ));
+
+        assert!(matches!(
+            mock_novel_info(r#"{"code": "240001", "tip": "系统繁忙"}"#).await,
+            Err(Error::NovelApi(_))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn novels_page_sort() -> Result<(), Error> {
+        let client = CiweimaoClient::new().await?;
+        let option = Options {
+            sort: Some(String::from("latest")),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            client.novels_page(&option, 0, 10).await,
+            Err(Error::NovelApi(msg)) if msg.contains("`Options::sort`")
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn chapter_versions() -> Result<(), Error> {
+        let mut client = CiweimaoClient::new().await?;
+        client.db = OnceCell::from(NovelDB::temp().await?);
+
+        let info_old = ChapterInfo {
+            identifier: Identifier::Id(1),
+            update_time: NaiveDateTime::from_str("2020-07-08T15:25:15").ok(),
+            ..Default::default()
+        };
+        let info_new = ChapterInfo {
+            identifier: Identifier::Id(1),
+            update_time: NaiveDateTime::from_str("2020-07-09T15:25:15").ok(),
+            ..Default::default()
+        };
+
+        let db = client.db().await?;
+        db.insert_text(&info_old, "旧的内容").await?;
+        assert!(!db.keep_version(&info_new, "旧的内容").await?);
+        assert!(db.keep_version(&info_new, "新的内容").await?);
+        db.update_text(&info_new, "新的内容").await?;
+
+        let versions = client.chapter_versions(&info_new).await?;
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0].id, ChapterVersion::CURRENT);
+        assert_eq!(versions[1].update_time, info_old.update_time);
+
+        let content_infos = client
+            .content_infos_version(&info_new, versions[1].id)
+            .await?;
+        assert!(matches!(&content_infos[0], ContentInfo::Text(text) if text == "旧的内容"));
+        assert!(matches!(
+            client
+                .content_infos_version(&info_new, versions[1].id + 1)
+                .await,
+            Err(Error::NovelApi(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_all_tags() -> Result<(), Error> {
+        let tags = CiweimaoClient::parse_all_tags("百合, 自定义标签,,").unwrap();
+        assert_eq!(
+            tags.iter().map(|tag| tag.name.as_str()).collect::<Vec<_>>(),
+            vec!["百合", "自定义标签"]
+        );
+
+        assert!(CiweimaoClient::parse_all_tags("").is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_data_time() -> Result<(), Error> {
+        let expected = NaiveDateTime::from_str("2020-07-08T15:25:15")?;
+
+        assert_eq!(
+            CiweimaoClient::parse_data_time("2020-07-08 15:25:15"),
+            Some(expected)
+        );
+        assert_eq!(
+            CiweimaoClient::parse_data_time("2020-07-08"),
+            Some(NaiveDateTime::from_str("2020-07-08T00:00:00")?)
+        );
+        // 2020-07-08 07:25:15 UTC
+        assert_eq!(
+            CiweimaoClient::parse_data_time("1594193115"),
+            Some(expected)
+        );
+        assert_eq!(CiweimaoClient::parse_data_time(""), None);
+        assert_eq!(CiweimaoClient::parse_data_time("unknown"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_volume_infos() -> Result<(), Error> {
+        let data: VolumesData = serde_json::from_str(
+            r#"{
+                "chapter_list": [
+                    {
+                        "division_name": "第一卷",
+                        "chapter_list": [
+                            {
+                                "chapter_id": "100",
+                                "chapter_title": "免费章节",
+                                "word_count": "3000",
+                                "mtime": "2022-12-01 12:00:00",
+                                "is_valid": "1",
+                                "auth_access": "1",
+                                "is_paid": "0"
+                            },
+                            {
+                                "chapter_id": "101",
+                                "chapter_title": "VIP章节",
+                                "word_count": "3000",
+                                "mtime": "2022-12-02 12:00:00",
+                                "is_valid": "1",
+                                "auth_access": "0",
+                                "is_paid": "1"
+                            }
+                        ]
+                    }
+                ]
+            }"#,
+        )?;
+
+        let volume_infos = CiweimaoClient::parse_volume_infos(data)?;
+        let chapter_infos = &volume_infos[0].chapter_infos;
+
+        assert_eq!(chapter_infos[0].is_vip, Some(false));
+        assert!(chapter_infos[0].can_download());
+
+        assert_eq!(chapter_infos[1].is_vip, Some(true));
+        assert!(!chapter_infos[1].can_download());
+
+        Ok(())
+    }
+}
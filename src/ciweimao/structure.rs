@@ -1,4 +1,4 @@
-use serde::{Deserialize, Serialize};
+use serde::{de::IgnoredAny, Deserialize, Serialize};
 
 use crate::{CiweimaoClient, Error};
 
@@ -13,6 +13,35 @@ pub(crate) fn check_response(code: String, tip: Option<String>) -> Result<(), Er
     }
 }
 
+/// Codes of a response about a novel that has been delisted, is under review or is blocked
+const UNAVAILABLE_CODES: [&str; 3] = [
+    CiweimaoClient::NOVEL_DELISTED,
+    CiweimaoClient::NOVEL_UNDER_REVIEW,
+    CiweimaoClient::NOVEL_BLOCKED,
+];
+
+pub(crate) fn check_novel_response(code: String, tip: Option<String>) -> Result<(), Error> {
+    if UNAVAILABLE_CODES.contains(&code.as_str()) {
+        let reason = match tip {
+            Some(tip) if !tip.trim().is_empty() => tip.trim().to_string(),
+            _ => format!("ciweimao code: `{code}`"),
+        };
+        return Err(Error::NovelUnavailable { reason });
+    }
+
+    check_response(code, tip)
+}
+
+pub(crate) fn check_login_response(code: String, tip: Option<String>) -> Result<(), Error> {
+    if code == CiweimaoClient::INVALID_CREDENTIALS {
+        Err(Error::InvalidCredentials(
+            tip.unwrap_or_default().trim().to_string(),
+        ))
+    } else {
+        check_response(code, tip)
+    }
+}
+
 #[must_use]
 #[derive(Serialize)]
 pub(crate) struct UserInfoRequest {
@@ -42,6 +71,53 @@ pub(crate) struct UserInfoReaderInfo {
     pub reader_name: String,
 }
 
+#[must_use]
+#[derive(Serialize)]
+pub(crate) struct SignRecordRequest {
+    pub app_version: &'static str,
+    pub device_token: &'static str,
+    pub account: String,
+    pub login_token: String,
+}
+
+#[must_use]
+#[derive(Deserialize)]
+pub(crate) struct SignRecordResponse {
+    pub code: String,
+    pub tip: Option<String>,
+    pub data: Option<SignRecordData>,
+}
+
+#[must_use]
+#[derive(Deserialize)]
+pub(crate) struct SignRecordData {
+    pub sign_record_list: Vec<SignRecord>,
+}
+
+#[must_use]
+#[derive(Deserialize)]
+pub(crate) struct SignRecord {
+    pub date: String,
+    pub is_signed: String,
+}
+
+#[must_use]
+#[derive(Serialize)]
+pub(crate) struct SignInRequest {
+    pub app_version: &'static str,
+    pub device_token: &'static str,
+    pub account: String,
+    pub login_token: String,
+    pub task_type: u8,
+}
+
+#[must_use]
+#[derive(Deserialize)]
+pub(crate) struct SignInResponse {
+    pub code: String,
+    pub tip: Option<String>,
+}
+
 #[must_use]
 #[derive(Serialize)]
 pub(crate) struct NovelInfoRequest {
@@ -71,6 +147,8 @@ pub(crate) struct NovelInfoData {
 pub(crate) struct NovelInfoBookInfo {
     pub book_name: String,
     pub author_name: String,
+    #[serde(default)]
+    pub author_id: String,
     pub cover: String,
     pub description: String,
     pub total_word_count: String,
@@ -79,6 +157,49 @@ pub(crate) struct NovelInfoBookInfo {
     pub uptime: String,
     pub category_index: String,
     pub tag: String,
+    #[serde(default)]
+    pub total_click: String,
+    #[serde(default)]
+    pub total_favor: String,
+    #[serde(default)]
+    pub chapter_amount: String,
+}
+
+#[must_use]
+#[derive(Serialize)]
+pub(crate) struct AuthorInfoRequest {
+    pub app_version: &'static str,
+    pub device_token: &'static str,
+    pub account: String,
+    pub login_token: String,
+    pub reader_id: u32,
+}
+
+#[must_use]
+#[derive(Deserialize)]
+pub(crate) struct AuthorInfoResponse {
+    pub code: String,
+    pub tip: Option<String>,
+    pub data: Option<AuthorInfoData>,
+}
+
+#[must_use]
+#[derive(Deserialize)]
+pub(crate) struct AuthorInfoData {
+    pub reader_info: AuthorInfoReaderInfo,
+    #[serde(default)]
+    // Only the number of novels is used
+    pub book_list: Option<Vec<IgnoredAny>>,
+}
+
+#[must_use]
+#[derive(Deserialize)]
+pub(crate) struct AuthorInfoReaderInfo {
+    pub reader_name: String,
+    #[serde(default)]
+    pub avatar_url: String,
+    #[serde(default)]
+    pub signature: String,
 }
 
 #[must_use]
@@ -109,6 +230,10 @@ pub(crate) struct VolumesData {
 #[derive(Deserialize)]
 pub(crate) struct VolumesVolumeInfo {
     pub division_name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub cover: String,
     pub chapter_list: Vec<VolumesChapterInfo>,
 }
 
@@ -117,10 +242,17 @@ pub(crate) struct VolumesVolumeInfo {
 pub(crate) struct VolumesChapterInfo {
     pub chapter_id: String,
     pub chapter_title: String,
-    pub word_count: String,
-    pub mtime: String,
-    pub is_valid: String,
-    pub auth_access: String,
+    // The fields below are occasionally absent for brand-new chapters
+    #[serde(default)]
+    pub word_count: Option<String>,
+    #[serde(default)]
+    pub mtime: Option<String>,
+    #[serde(default)]
+    pub is_valid: Option<String>,
+    #[serde(default)]
+    pub auth_access: Option<String>,
+    #[serde(default)]
+    pub is_paid: Option<String>,
 }
 
 #[must_use]
@@ -164,6 +296,7 @@ pub(crate) struct SearchRequest {
     pub key: String,
     pub count: u16,
     pub page: u16,
+    pub category_index: Option<u16>,
 }
 
 #[must_use]
@@ -248,6 +381,7 @@ pub(crate) struct CategoryData {
 #[must_use]
 #[derive(Deserialize)]
 pub(crate) struct CategoryCategory {
+    pub category_name: String,
     pub category_detail: Vec<CategoryDetail>,
 }
 
@@ -317,6 +451,8 @@ pub(crate) struct NovelsResponse {
 #[derive(Deserialize)]
 pub(crate) struct NovelsData {
     pub book_list: Vec<NovelsInfo>,
+    #[serde(default)]
+    pub total: Option<String>,
 }
 
 #[must_use]
@@ -494,3 +630,45 @@ pub(crate) struct ShelfListData {
 pub(crate) struct ShelfList {
     pub shelf_id: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn check_login_response() -> Result<(), Error> {
+        let response: LoginResponse = serde_json::from_str(
+            r#"{"code":"100000","data":{"login_token":"token","reader_info":{"account":"account"}}}"#,
+        )?;
+        assert!(super::check_login_response(response.code, response.tip).is_ok());
+
+        let response: LoginResponse =
+            serde_json::from_str(r#"{"code":"210002","tip":"用户名或密码错误"}"#)?;
+        assert!(matches!(
+            super::check_login_response(response.code, response.tip),
+            Err(Error::InvalidCredentials(msg)) if msg == "用户名或密码错误"
+        ));
+
+        let response: LoginResponse =
+            serde_json::from_str(r#"{"code":"300001","tip":"系统繁忙"}"#)?;
+        assert!(matches!(
+            super::check_login_response(response.code, response.tip),
+            Err(Error::NovelApi(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn volumes_chapter_info() -> Result<(), Error> {
+        let chapter: VolumesChapterInfo = serde_json::from_str(
+            r#"{"chapter_id":"1","chapter_title":"第一章","mtime":"2020-07-08 15:25:15","is_valid":"1","auth_access":"1"}"#,
+        )?;
+        assert_eq!(chapter.word_count, None);
+        assert_eq!(chapter.is_valid.as_deref(), Some("1"));
+
+        Ok(())
+    }
+}
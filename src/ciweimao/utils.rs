@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, sync::Arc, time::Duration};
 
 use boring::{
     sha,
@@ -9,11 +9,14 @@ use parking_lot::RwLock;
 use reqwest::Response;
 use semver::{Version, VersionReq};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use tokio::{fs, sync::OnceCell};
+use tokio::{
+    fs,
+    sync::{OnceCell, Semaphore},
+};
 use tracing::{error, info, warn};
 use url::Url;
 
-use crate::{CiweimaoClient, Error, HTTPClient, NovelDB};
+use crate::{CiweimaoClient, Error, HTTPClient, ImageLimits, LoginRetry, NovelDB};
 
 #[must_use]
 #[derive(Serialize, Deserialize)]
@@ -23,12 +26,27 @@ struct Config {
     login_token: String,
 }
 
+/// The session exported by [`crate::Client::export_session`]
+#[must_use]
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Session {
+    pub(crate) account: String,
+    pub(crate) login_token: String,
+}
+
 impl CiweimaoClient {
-    const APP_NAME: &str = "ciweimao";
+    pub(crate) const APP_NAME: &str = "ciweimao";
+
+    /// Largest page size requested from ciweimao, larger sizes are clamped
+    pub const MAX_PAGE_SIZE: u16 = 50;
 
     pub(crate) const OK: &str = "100000";
     pub(crate) const LOGIN_EXPIRED: &str = "200100";
+    pub(crate) const INVALID_CREDENTIALS: &str = "210002";
     pub(crate) const NOT_FOUND: &str = "320001";
+    pub(crate) const NOVEL_DELISTED: &str = "320002";
+    pub(crate) const NOVEL_UNDER_REVIEW: &str = "320003";
+    pub(crate) const NOVEL_BLOCKED: &str = "320004";
 
     pub(crate) const APP_VERSION: &str = "2.9.293";
     pub(crate) const DEVICE_TOKEN: &str = "ciweimao_";
@@ -46,22 +64,64 @@ impl CiweimaoClient {
 
     const AES_KEY: &str = "zG2nSeEfSHfvTCHy5LCcqtBbQehKNLXn";
 
+    const CAPTCHA_TIMEOUT: Duration = Duration::from_secs(300);
+
     /// Create a ciweimao client
     pub async fn new() -> Result<Self, Error> {
         let (account, login_token) = CiweimaoClient::load_config_file().await?;
 
         Ok(Self {
             proxy: None,
+            image_proxy: None,
             no_proxy: false,
             cert_path: None,
+            pinned_cert: None,
+            min_tls_version: None,
+            pool_idle_timeout: None,
+            reqwest_client: None,
+            login_retry: LoginRetry::default(),
+            host: None,
+            stale_ok: false,
+            image_limits: ImageLimits::default(),
+            image_decodes: Semaphore::new(crate::default_image_decodes()),
+            write_behind_images: false,
+            no_cache: false,
+            image_cache_dir: None,
+            transport: None,
+            metrics: None,
+            captcha_timeout: CiweimaoClient::CAPTCHA_TIMEOUT,
+            persist_token: true,
             client: OnceCell::new(),
             client_rss: OnceCell::new(),
             db: OnceCell::new(),
+            categories: OnceCell::new(),
+            category_groups: OnceCell::new(),
+            tags: OnceCell::new(),
+            taxonomy: OnceCell::new(),
             account: RwLock::new(account),
             login_token: RwLock::new(login_token),
         })
     }
 
+    /// Set how long to wait for the Geetest captcha to be solved in the browser, the default is 5 minutes
+    pub fn captcha_timeout(&mut self, timeout: Duration) {
+        self.captcha_timeout = timeout;
+    }
+
+    /// Set whether the token is saved to the config file on shutdown or drop, the default is true
+    pub fn persist_token(&mut self, flag: bool) {
+        self.persist_token = flag;
+    }
+
+    /// Wrap the client in an `Arc` so it can be shared across tasks
+    ///
+    /// All `Client` methods take `&self`, so the `Arc` can be cloned into spawned tasks,
+    /// configure the client before sharing it, the token is saved when the last handle is dropped
+    #[must_use]
+    pub fn into_shared(self) -> Arc<Self> {
+        Arc::new(self)
+    }
+
     async fn load_config_file() -> Result<(Option<String>, Option<String>), Error> {
         let config_file_path = CiweimaoClient::config_file_path()?;
 
@@ -134,6 +194,12 @@ impl CiweimaoClient {
                     .proxy(self.proxy.clone())
                     .no_proxy(self.no_proxy)
                     .cert(self.cert_path.clone())
+                    .pinned_cert(self.pinned_cert.clone())
+                    .min_tls_version(self.min_tls_version)
+                    .pool_idle_timeout(self.pool_idle_timeout)
+                    .reqwest_client(self.reqwest_client.clone())
+                    .transport(self.transport.clone())
+                    .metrics(self.metrics.clone())
                     .build()
                     .await
             })
@@ -144,13 +210,23 @@ impl CiweimaoClient {
     async fn client_rss(&self) -> Result<&HTTPClient, Error> {
         self.client_rss
             .get_or_try_init(|| async {
+                let (proxy, no_proxy) = match &self.image_proxy {
+                    Some(image_proxy) => (Some(image_proxy.clone()), false),
+                    None => (self.proxy.clone(), self.no_proxy),
+                };
+
                 HTTPClient::builder(CiweimaoClient::APP_NAME)
                     .accept("image/*,*/*;q=0.8")
                     .accept_language("zh-CN,zh-Hans;q=0.9")
                     .user_agent(CiweimaoClient::USER_AGENT_RSS)
-                    .proxy(self.proxy.clone())
-                    .no_proxy(self.no_proxy)
+                    .proxy(proxy)
+                    .no_proxy(no_proxy)
                     .cert(self.cert_path.clone())
+                    .min_tls_version(self.min_tls_version)
+                    .pool_idle_timeout(self.pool_idle_timeout)
+                    .reqwest_client(self.reqwest_client.clone())
+                    .transport(self.transport.clone())
+                    .metrics(self.metrics.clone())
                     .build()
                     .await
             })
@@ -164,33 +240,38 @@ impl CiweimaoClient {
             .await
     }
 
+    /// Join the path to the host set by [`crate::Client::host`], or the production host
+    #[must_use]
+    fn api_url<T>(&self, path: T) -> String
+    where
+        T: AsRef<str>,
+    {
+        let host = match &self.host {
+            Some(host) => host.as_str().trim_end_matches('/'),
+            None => CiweimaoClient::HOST,
+        };
+
+        host.to_string() + path.as_ref()
+    }
+
     #[inline]
     pub(crate) async fn get_query<T, E>(&self, url: T, query: &E) -> Result<Response, Error>
     where
         T: AsRef<str>,
         E: Serialize,
     {
-        let response = self
-            .client()
-            .await?
-            .get(CiweimaoClient::HOST.to_string() + url.as_ref())
-            .query(query)
-            .send()
+        let client = self.client().await?;
+        let response = client
+            .send(client.get(self.api_url(url.as_ref())).query(query))
             .await?;
-        crate::check_status(
-            response.status(),
-            format!("HTTP request failed: `{}`", url.as_ref()),
-        )?;
-
-        Ok(response)
+        crate::check_status(response, format!("HTTP request failed: `{}`", url.as_ref())).await
     }
 
     #[inline]
     pub(crate) async fn get_rss(&self, url: &Url) -> Result<Response, Error> {
-        let response = self.client_rss().await?.get(url.clone()).send().await?;
-        crate::check_status(response.status(), format!("HTTP request failed: `{url}`"))?;
-
-        Ok(response)
+        let client = self.client_rss().await?;
+        let response = client.send(client.get(url.clone())).await?;
+        crate::check_status(response, format!("HTTP request failed: `{url}`")).await
     }
 
     #[inline]
@@ -200,33 +281,79 @@ impl CiweimaoClient {
         E: Serialize,
         R: DeserializeOwned,
     {
-        let response = self
-            .client()
-            .await?
-            .post(CiweimaoClient::HOST.to_string() + url.as_ref())
-            .form(form)
-            .send()
+        let client = self.client().await?;
+        let response = client
+            .send(client.post(self.api_url(url.as_ref())).form(form))
             .await?;
-        crate::check_status(
-            response.status(),
-            format!("HTTP request failed: `{}`", url.as_ref()),
-        )?;
+        let response =
+            crate::check_status(response, format!("HTTP request failed: `{}`", url.as_ref()))
+                .await?;
 
+        let content_type = crate::content_type(&response);
         let bytes = response.bytes().await?;
-        let bytes =
-            CiweimaoClient::aes_256_cbc_base64_decrypt(CiweimaoClient::get_default_key(), &bytes)?;
 
-        let str = simdutf8::basic::from_utf8(&bytes)?;
-        Ok(serde_json::from_str(str)?)
+        CiweimaoClient::parse_response(&bytes, content_type.as_deref())
+    }
+
+    /// Decrypt and deserialize the body of a response, if the body is not encrypted JSON,
+    /// e.g. an HTML error page, the error contains the beginning of the body
+    fn parse_response<R>(bytes: &[u8], content_type: Option<&str>) -> Result<R, Error>
+    where
+        R: DeserializeOwned,
+    {
+        let decrypted = match CiweimaoClient::aes_256_cbc_base64_decrypt(
+            CiweimaoClient::get_default_key(),
+            bytes,
+        ) {
+            Ok(decrypted) => decrypted,
+            Err(error) => {
+                let body = String::from_utf8_lossy(bytes);
+                return Err(Error::NovelApi(format!(
+                    "Failed to decrypt the response: {error}, body: `{}`",
+                    CiweimaoClient::snippet(&body)
+                )));
+            }
+        };
+
+        let str = crate::decode_text(decrypted, content_type)?;
+        match serde_json::from_str(&str) {
+            Ok(result) => Ok(result),
+            Err(error) if error.is_syntax() || error.is_eof() => Err(Error::NovelApi(format!(
+                "The decrypted response is not JSON: {error}, body: `{}`",
+                CiweimaoClient::snippet(&str)
+            ))),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    #[must_use]
+    fn snippet(text: &str) -> String {
+        const MAX_CHARS: usize = 200;
+
+        let text = text.trim();
+        match text.char_indices().nth(MAX_CHARS) {
+            Some((index, _)) => format!("{}...", &text[..index]),
+            None => text.to_string(),
+        }
     }
 
     #[must_use]
     #[inline]
-    fn get_default_key() -> &'static [u8; 32] {
+    pub(crate) fn get_default_key() -> &'static [u8; 32] {
         static AES_KEY: SyncOnceCell<[u8; 32]> = SyncOnceCell::new();
         AES_KEY.get_or_init(|| sha::sha256(CiweimaoClient::AES_KEY.as_bytes()))
     }
 
+    /// The key used to decrypt the content of a chapter, derived from the command of the chapter
+    #[must_use]
+    #[inline]
+    pub(crate) fn chapter_key<T>(cmd: T) -> [u8; 32]
+    where
+        T: AsRef<str>,
+    {
+        sha::sha256(cmd.as_ref().as_bytes())
+    }
+
     #[inline]
     pub(crate) fn aes_256_cbc_base64_decrypt<T, E>(key: T, data: E) -> Result<Vec<u8>, Error>
     where
@@ -242,8 +369,27 @@ impl CiweimaoClient {
         Ok(result)
     }
 
+    #[cfg(test)]
+    pub(crate) fn aes_256_cbc_base64_encrypt<T, E>(key: T, data: E) -> Result<Vec<u8>, Error>
+    where
+        T: AsRef<[u8]>,
+        E: AsRef<[u8]>,
+    {
+        let cipher = Cipher::aes_256_cbc();
+        let encrypted = symm::encrypt(cipher, key.as_ref(), Some(&[0; 16]), data.as_ref())?;
+
+        Ok(base64_simd::STANDARD
+            .encode_to_string(encrypted)
+            .into_bytes())
+    }
+
     pub(crate) fn do_shutdown(&self) -> Result<(), Error> {
-        if self.has_token() {
+        if !self.persist_token {
+            info!("The token is not saved, as persisting it is disabled");
+
+            *self.account.write() = None;
+            *self.login_token.write() = None;
+        } else if self.has_token() {
             let config = Config {
                 version: Version::parse(CiweimaoClient::CONFIG_VERSION).unwrap(),
                 account: self.account(),
@@ -272,3 +418,96 @@ impl Drop for CiweimaoClient {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use hex_simd::AsciiCase;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn key_derivation() -> Result<(), Error> {
+        assert_eq!(
+            hex_simd::encode_to_string(CiweimaoClient::get_default_key(), AsciiCase::Lower),
+            "957b0051ed4d644c3ab40c4cf70d53a6adad53895adbe3f033c7af8f60bec81d"
+        );
+        assert_eq!(
+            hex_simd::encode_to_string(CiweimaoClient::chapter_key("command"), AsciiCase::Lower),
+            "5d347fd948b66308f502c3f65c8f7e12ff1c5cf8c760bcdfb188ae1ec7b8b618"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_response() -> Result<(), Error> {
+        let encrypted = CiweimaoClient::aes_256_cbc_base64_encrypt(
+            CiweimaoClient::get_default_key(),
+            "<html><body>403 Forbidden</body></html>",
+        )?;
+        assert!(matches!(
+            CiweimaoClient::parse_response::<serde_json::Value>(&encrypted, None),
+            Err(Error::NovelApi(msg)) if msg.contains("403 Forbidden")
+        ));
+
+        assert!(matches!(
+            CiweimaoClient::parse_response::<serde_json::Value>(b"<html>403 Forbidden</html>", None),
+            Err(Error::NovelApi(msg)) if msg.contains("403 Forbidden")
+        ));
+
+        assert!(matches!(
+            CiweimaoClient::parse_response::<serde_json::Value>(
+                "<html><body>抱歉，该服务暂不支持您所在的地区</body></html>".as_bytes(),
+                None
+            ),
+            Err(Error::NovelApi(msg)) if msg.contains("地区")
+        ));
+
+        let encrypted = CiweimaoClient::aes_256_cbc_base64_encrypt(
+            CiweimaoClient::get_default_key(),
+            r#"{"code":100000}"#,
+        )?;
+        assert!(matches!(
+            CiweimaoClient::parse_response::<Config>(&encrypted, None),
+            Err(Error::SerdeJson(_))
+        ));
+
+        assert_eq!(
+            CiweimaoClient::snippet(&"字".repeat(300)).chars().count(),
+            203
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn aes_256_cbc_base64() -> Result<(), Error> {
+        let plaintext = r#"{"code":"100000"}"#;
+        let ciphertext = "IT+LcNazRBcK54/p1lMtc+Sa0VCPIUiWrrSBnOhRaVs=";
+
+        assert_eq!(
+            CiweimaoClient::aes_256_cbc_base64_encrypt(
+                CiweimaoClient::get_default_key(),
+                plaintext
+            )?,
+            ciphertext.as_bytes()
+        );
+        assert_eq!(
+            CiweimaoClient::aes_256_cbc_base64_decrypt(
+                CiweimaoClient::get_default_key(),
+                ciphertext
+            )?,
+            plaintext.as_bytes()
+        );
+
+        let key = CiweimaoClient::chapter_key("command");
+        let encrypted = CiweimaoClient::aes_256_cbc_base64_encrypt(key, "第一章")?;
+        assert_eq!(
+            CiweimaoClient::aes_256_cbc_base64_decrypt(key, encrypted)?,
+            "第一章".as_bytes()
+        );
+
+        Ok(())
+    }
+}
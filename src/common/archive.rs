@@ -0,0 +1,245 @@
+use std::{
+    fs::File,
+    io::Read,
+    path::{Component, Path, PathBuf},
+};
+
+use tar::{Archive, Builder, Header};
+use tokio::fs;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{Error, NovelDB};
+
+const DATA_DIR: &str = "data";
+const CONFIG_DIR: &str = "config";
+/// The entry holding the Unix timestamp in milliseconds of the last write to the exported database
+const LAST_WRITE: &str = "last_write";
+
+/// Bundle a snapshot of the database and all config files into a tar archive
+pub(crate) async fn export_cache<T>(app_name: &str, db: &NovelDB, out: T) -> Result<(), Error>
+where
+    T: AsRef<Path>,
+{
+    let out = out.as_ref();
+
+    db.flush().await?;
+    let last_write = db.last_write().await?;
+    let snapshot_path = std::env::temp_dir().join(format!("{}.db", Uuid::new_v4()));
+    db.backup(&snapshot_path).await?;
+
+    let result = write_archive(app_name, &snapshot_path, last_write, out);
+    fs::remove_file(&snapshot_path).await?;
+    result?;
+
+    info!("The cache has been exported to: `{}`", out.display());
+
+    Ok(())
+}
+
+fn write_archive(
+    app_name: &str,
+    snapshot_path: &Path,
+    last_write: Option<i64>,
+    out: &Path,
+) -> Result<(), Error> {
+    let mut builder = Builder::new(File::create(out)?);
+
+    if let Some(last_write) = last_write {
+        let data = last_write.to_string();
+        let mut header = Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, LAST_WRITE, data.as_bytes())?;
+    }
+
+    builder.append_path_with_name(
+        snapshot_path,
+        Path::new(DATA_DIR).join(NovelDB::db_path(app_name)?.file_name().unwrap()),
+    )?;
+
+    let config_dir = crate::config_dir_path(app_name)?;
+    if config_dir.is_dir() {
+        for entry in std::fs::read_dir(config_dir)? {
+            let entry = entry?;
+
+            if entry.file_type()?.is_file() {
+                builder.append_path_with_name(
+                    entry.path(),
+                    Path::new(CONFIG_DIR).join(entry.file_name()),
+                )?;
+            }
+        }
+    }
+
+    builder.into_inner()?;
+
+    Ok(())
+}
+
+/// Restore the database and config files from an archive created by `export_cache`
+///
+/// If the local database has been written after the one in the archive, the import is refused unless `force` is set,
+/// the times compared are the last writes recorded in the databases, not the modification times of the files
+pub(crate) async fn import_cache<T>(app_name: &str, archive: T, force: bool) -> Result<(), Error>
+where
+    T: AsRef<Path>,
+{
+    let archive = archive.as_ref();
+    let db_path = NovelDB::db_path(app_name)?;
+
+    if !force && fs::try_exists(&db_path).await? {
+        let local = NovelDB::open(&db_path).await?;
+        local.migrate().await?;
+        let local_last_write = local.last_write().await?;
+        local.close().await?;
+
+        // An archive without the time of its last write is treated as older than any recorded write
+        if local_last_write > archive_last_write(archive)? {
+            return Err(Error::NovelApi(format!(
+                "The local cache is newer than the archive, use `force` to overwrite it: `{}`",
+                db_path.display()
+            )));
+        }
+    }
+
+    let data_dir = db_path.parent().unwrap().to_path_buf();
+    let config_dir = crate::config_dir_path(app_name)?;
+    fs::create_dir_all(&data_dir).await?;
+    fs::create_dir_all(&config_dir).await?;
+
+    unpack_archive(archive, &db_path, &data_dir, &config_dir)?;
+
+    info!("The cache has been imported from: `{}`", archive.display());
+
+    Ok(())
+}
+
+fn unpack_archive(
+    archive: &Path,
+    db_path: &Path,
+    data_dir: &Path,
+    config_dir: &Path,
+) -> Result<(), Error> {
+    let mut tar = Archive::new(File::open(archive)?);
+
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+
+        if let Some(path) = destination(&entry.path()?, data_dir, config_dir) {
+            if path == db_path {
+                // Stale WAL files would otherwise be applied to the restored database
+                for suffix in ["-wal", "-shm"] {
+                    let mut wal_path = db_path.as_os_str().to_os_string();
+                    wal_path.push(suffix);
+
+                    if Path::new(&wal_path).exists() {
+                        std::fs::remove_file(&wal_path)?;
+                    }
+                }
+            }
+
+            entry.unpack(&path)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn archive_last_write(archive: &Path) -> Result<Option<i64>, Error> {
+    let mut tar = Archive::new(File::open(archive)?);
+
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+
+        if entry.path()? == Path::new(LAST_WRITE) {
+            let mut data = String::new();
+            entry.read_to_string(&mut data)?;
+
+            return Ok(data.trim().parse().ok());
+        }
+    }
+
+    Ok(None)
+}
+
+fn destination(path: &Path, data_dir: &Path, config_dir: &Path) -> Option<PathBuf> {
+    let mut components = path.components();
+
+    let dir = components.next()?.as_os_str();
+    let file_name = match components.next()? {
+        Component::Normal(file_name) => file_name,
+        _ => return None,
+    };
+    if components.next().is_some() {
+        return None;
+    }
+
+    if dir == DATA_DIR {
+        Some(data_dir.join(file_name))
+    } else if dir == CONFIG_DIR {
+        Some(config_dir.join(file_name))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::{ChapterInfo, FindTextResult, Identifier};
+
+    #[tokio::test]
+    async fn export_import_cache() -> Result<(), Error> {
+        let contents = "test-contents";
+        let chapter_info = ChapterInfo {
+            identifier: Identifier::Id(0),
+            ..Default::default()
+        };
+
+        let db = NovelDB::new("test-app-export").await?;
+        db.insert_text(&chapter_info, contents).await?;
+
+        let archive_path = std::env::temp_dir().join(format!("{}.tar", Uuid::new_v4()));
+        export_cache("test-app-export", &db, &archive_path).await?;
+        db.drop().await?;
+
+        import_cache("test-app-import", &archive_path, true).await?;
+
+        let db = NovelDB::new("test-app-import").await?;
+        assert_eq!(
+            db.find_text(&chapter_info).await?,
+            FindTextResult::Ok(contents.to_string())
+        );
+        assert_eq!(db.last_write().await?, archive_last_write(&archive_path)?);
+
+        // A write after the export makes the local cache newer than the archive
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        let newer_chapter_info = ChapterInfo {
+            identifier: Identifier::Id(1),
+            ..Default::default()
+        };
+        db.insert_text(&newer_chapter_info, contents).await?;
+        db.close().await?;
+
+        assert!(matches!(
+            import_cache("test-app-import", &archive_path, false).await,
+            Err(Error::NovelApi(_))
+        ));
+        import_cache("test-app-import", &archive_path, true).await?;
+        fs::remove_file(&archive_path).await?;
+
+        let db = NovelDB::new("test-app-import").await?;
+        assert_eq!(
+            db.find_text(&newer_chapter_info).await?,
+            FindTextResult::None
+        );
+        db.drop().await?;
+
+        Ok(())
+    }
+}
@@ -1,14 +1,24 @@
 use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    future::Future,
+    io::Write,
     ops::{Range, RangeFrom, RangeTo},
-    path::Path,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
 };
 
 use async_trait::async_trait;
 use chrono::NaiveDateTime;
-use image::DynamicImage;
+use futures_util::{future, stream, stream::BoxStream, StreamExt, TryStreamExt};
+use image::{DynamicImage, ImageFormat};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tracing::{error, warn};
 use url::Url;
 
-use crate::Error;
+use crate::{EndpointMetrics, Error};
 
 /// Logged-in user information
 #[must_use]
@@ -18,9 +28,35 @@ pub struct UserInfo {
     pub nickname: String,
 }
 
+/// Daily sign-in status of the logged-in user
+#[must_use]
+#[derive(Debug, PartialEq, Eq)]
+pub struct SignInStatus {
+    /// Has the user signed in today
+    pub is_signed_in: bool,
+}
+
+/// A result that may be partially successful
+#[must_use]
+#[derive(Debug)]
+pub struct PartialResult<T> {
+    /// The successfully obtained part
+    pub value: T,
+    /// Errors of the failed parts
+    pub errors: Vec<Error>,
+}
+
+impl<T> PartialResult<T> {
+    /// Is every part successful
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
 /// Novel information
 #[must_use]
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct NovelInfo {
     /// Novel id
     pub id: u32,
@@ -28,6 +64,8 @@ pub struct NovelInfo {
     pub name: String,
     /// Author name
     pub author_name: String,
+    /// Author id, used to get the author information
+    pub author_id: Option<u32>,
     /// Url of the novel cover
     pub cover_url: Option<Url>,
     /// Novel introduction
@@ -42,8 +80,19 @@ pub struct NovelInfo {
     pub update_time: Option<NaiveDateTime>,
     /// Novel category
     pub category: Option<Category>,
-    /// Novel tags
+    /// Novel tags, only the system tags that can be used for searching
     pub tags: Option<Vec<Tag>>,
+    /// All tags returned by the website, including those that are not system tags
+    pub all_tags: Option<Vec<Tag>>,
+    /// Number of times the novel has been viewed
+    pub view_count: Option<u32>,
+    /// Number of users who added the novel to their bookshelf
+    pub favorite_count: Option<u32>,
+    /// Novel rating given by readers
+    pub rating: Option<f32>,
+    /// Number of chapters, `None` if the site does not provide it with the novel information,
+    /// in which case it can be counted from [`Client::volume_infos`]
+    pub chapter_count: Option<u32>,
 }
 
 impl PartialEq for NovelInfo {
@@ -52,9 +101,23 @@ impl PartialEq for NovelInfo {
     }
 }
 
+/// Author information
+#[must_use]
+#[derive(Debug, Default)]
+pub struct AuthorInfo {
+    /// Author name
+    pub name: String,
+    /// Url of the author avatar
+    pub avatar_url: Option<Url>,
+    /// Author introduction
+    pub introduction: Option<String>,
+    /// Number of novels written by the author
+    pub novel_count: Option<u32>,
+}
+
 /// Novel category
 #[must_use]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Category {
     /// Category id
     pub id: Option<u16>,
@@ -68,9 +131,19 @@ impl ToString for Category {
     }
 }
 
-/// Novel tag
+/// A group of novel categories
 #[must_use]
 #[derive(Debug, Clone)]
+pub struct CategoryGroup {
+    /// Group name
+    pub name: String,
+    /// Categories in the group
+    pub categories: Vec<Category>,
+}
+
+/// Novel tag
+#[must_use]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Tag {
     /// Tag id
     pub id: Option<u16>,
@@ -87,19 +160,26 @@ impl ToString for Tag {
 /// Volume information
 pub type VolumeInfos = Vec<VolumeInfo>;
 
+/// Table of contents, the volume titles with the identifiers and titles of their chapters
+pub type Toc = Vec<(String, Vec<(Identifier, String)>)>;
+
 /// Volume information
 #[must_use]
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct VolumeInfo {
     /// Volume title
     pub title: String,
+    /// Volume description
+    pub description: Option<String>,
+    /// Url of the volume cover
+    pub cover_url: Option<Url>,
     /// Chapter information
     pub chapter_infos: Vec<ChapterInfo>,
 }
 
 /// Chapter information
 #[must_use]
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ChapterInfo {
     /// Chapter identifier
     pub identifier: Identifier,
@@ -132,11 +212,74 @@ impl ChapterInfo {
     pub fn can_download(&self) -> bool {
         self.is_accessible() && self.is_valid()
     }
+
+    /// Is this a VIP chapter that the current user has purchased
+    pub fn is_owned(&self) -> bool {
+        self.is_vip == Some(true) && self.is_accessible()
+    }
+}
+
+/// Accessibility of all chapters of a novel
+#[must_use]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct NovelAccessibility {
+    /// Total number of chapters
+    pub chapter_count: usize,
+    /// Number of chapters that can not be downloaded
+    pub locked_chapter_count: usize,
+}
+
+impl NovelAccessibility {
+    /// Can every chapter be downloaded
+    #[must_use]
+    pub fn is_fully_accessible(&self) -> bool {
+        self.locked_chapter_count == 0
+    }
+}
+
+impl From<&VolumeInfos> for NovelAccessibility {
+    fn from(volume_infos: &VolumeInfos) -> Self {
+        let mut result = NovelAccessibility::default();
+
+        for chapter_info in volume_infos
+            .iter()
+            .flat_map(|volume_info| &volume_info.chapter_infos)
+        {
+            result.chapter_count += 1;
+            if !chapter_info.can_download() {
+                result.locked_chapter_count += 1;
+            }
+        }
+
+        result
+    }
+}
+
+/// All metadata of a novel except the content
+#[must_use]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NovelSnapshot {
+    /// Novel information
+    pub novel_info: NovelInfo,
+    /// Volume information
+    pub volume_infos: VolumeInfos,
+}
+
+/// Result of [`Client::refresh_novel`]
+#[must_use]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct RefreshReport {
+    /// Number of chapters downloaded because they were missing or outdated
+    pub fetched: usize,
+    /// Number of chapters already up to date in the cache, or not downloadable
+    pub skipped: usize,
+    /// Number of chapters that failed to download
+    pub failed: usize,
 }
 
 /// Chapter identifier
 #[must_use]
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Identifier {
     /// Chapter id
     Id(u32),
@@ -144,11 +287,23 @@ pub enum Identifier {
     Url(Url),
 }
 
-impl ToString for Identifier {
-    fn to_string(&self) -> String {
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Identifier::Id(id) => id.to_string(),
-            Identifier::Url(url) => url.to_string(),
+            Identifier::Id(id) => write!(f, "{id}"),
+            Identifier::Url(url) => write!(f, "{url}"),
+        }
+    }
+}
+
+impl FromStr for Identifier {
+    type Err = Error;
+
+    /// Parse the string produced by `to_string`, a number is an id, anything else must be a Url
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str.parse::<u32>() {
+            Ok(id) => Ok(Identifier::Id(id)),
+            Err(_) => Ok(Identifier::Url(Url::parse(str)?)),
         }
     }
 }
@@ -166,6 +321,65 @@ pub enum ContentInfo {
     Image(Url),
 }
 
+/// Content of a chapter returned by [`Client::chapter_preview`]
+#[must_use]
+#[derive(Debug)]
+pub struct ChapterPreview {
+    /// Content information
+    pub content_infos: ContentInfos,
+    /// Whether this is the free preview of a locked chapter rather than the full content
+    pub is_preview: bool,
+}
+
+/// Space used by the cache, returned by [`Client::cache_size`]
+#[must_use]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheSize {
+    /// Bytes of the compressed chapter texts and previews
+    pub texts: u64,
+    /// Bytes of the compressed images in the database, or of the image files if [`Client::image_cache_fs`] is used
+    pub images: u64,
+    /// Bytes used in total, the database including its overhead, plus the image files
+    pub total: u64,
+}
+
+/// Result of [`Client::verify_cached_chapter`]
+#[must_use]
+#[derive(Debug, PartialEq, Eq)]
+pub enum CacheVerdict {
+    /// The cached text agrees with the word count, or the server gives no word count
+    Match,
+    /// The cached text is much shorter or longer than the word count, it may be truncated or corrupted
+    Mismatch {
+        /// Number of characters of the cached text, whitespace and images excluded
+        cached: usize,
+        /// Word count given by the server
+        expected: u16,
+    },
+    /// The chapter is not cached or the cache is outdated
+    NotCached,
+}
+
+/// Content information with the images downloaded and decoded
+#[must_use]
+#[derive(Debug)]
+pub enum ContentInfoResolved {
+    /// Text content
+    Text(String),
+    /// Image content
+    Image(DynamicImage),
+}
+
+/// Novel information with the cover downloaded and decoded
+#[must_use]
+#[derive(Debug)]
+pub struct NovelCard {
+    /// Novel information
+    pub info: NovelInfo,
+    /// Cover of the novel, `None` if there is no cover url or the download failed
+    pub cover: Option<DynamicImage>,
+}
+
 /// Options used by the search
 #[derive(Debug, Default)]
 pub struct Options {
@@ -177,12 +391,85 @@ pub struct Options {
     pub category: Option<Category>,
     /// Included tags
     pub tags: Option<Vec<Tag>>,
+    /// How the included tags are combined
+    pub tag_logic: TagLogic,
     /// Excluded tags
     pub excluded_tags: Option<Vec<Tag>>,
     /// The number of days since the last update
     pub update_days: Option<u8>,
     /// Word count
     pub word_count: Option<WordCountRange>,
+    /// Sort token of the site, e.g. `latest` for sfacg, the default order of the site is used if it is not set
+    pub sort: Option<String>,
+}
+
+impl Options {
+    /// Return an error if more than one tag is included and the site does not support the tag logic,
+    /// the logic makes no difference for a single tag
+    pub(crate) fn check_tag_logic(&self, supported: TagLogic) -> Result<(), Error> {
+        let tag_count = self.tags.as_ref().map_or(0, |tags| tags.len());
+
+        if tag_count > 1 && self.tag_logic != supported {
+            return Err(Error::NovelApi(format!(
+                "The tag logic `{:?}` is not supported, only `{supported:?}` is supported by this client",
+                self.tag_logic
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// How the included tags are combined
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TagLogic {
+    /// Novels that have all the tags
+    #[default]
+    And,
+    /// Novels that have any of the tags
+    Or,
+}
+
+/// A page of the novels matching the options
+#[must_use]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct NovelsPage {
+    /// Novel id of the page
+    pub ids: Vec<u32>,
+    /// The total number of matching novels, `None` if the site does not return it
+    pub total: Option<u32>,
+}
+
+/// Page size used when a size of 0 is passed to `search_infos` or `novels`
+pub const DEFAULT_PAGE_SIZE: u16 = 12;
+
+/// Replace a page size of 0 with [`DEFAULT_PAGE_SIZE`] and clamp it to `max`
+#[must_use]
+pub(crate) fn page_size(size: u16, max: u16) -> u16 {
+    if size == 0 {
+        DEFAULT_PAGE_SIZE
+    } else if size > max {
+        warn!("The page size {size} is larger than the maximum {max}, clamped to {max}");
+        max
+    } else {
+        size
+    }
+}
+
+/// Fetch all categories with the tags of each category, used by [`Client::browse_taxonomy`]
+pub(crate) async fn fetch_taxonomy<T>(client: &T) -> Result<Vec<(Category, Vec<Tag>)>, Error>
+where
+    T: Client + Sync,
+{
+    let tasks = client
+        .categories()
+        .await?
+        .iter()
+        .map(|category| async move {
+            Ok::<_, Error>((category.clone(), client.tags_for_category(category).await?))
+        });
+
+    future::try_join_all(tasks).await
 }
 
 /// Word count range
@@ -196,26 +483,266 @@ pub enum WordCountRange {
     RangeTo(RangeTo<u32>),
 }
 
+/// Minimum TLS version accepted by the client
+///
+/// TLS is provided by rustls, which only implements TLS 1.2 and TLS 1.3
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TlsVersion {
+    /// TLS 1.2
+    Tls1_2,
+    /// TLS 1.3
+    Tls1_3,
+}
+
+/// Limits applied when decoding images, to prevent hostile images from exhausting memory
+#[must_use]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageLimits {
+    /// Maximum image width in pixels
+    pub max_width: u32,
+    /// Maximum image height in pixels
+    pub max_height: u32,
+    /// Maximum size of the encoded image in bytes
+    pub max_bytes: usize,
+}
+
+impl Default for ImageLimits {
+    fn default() -> Self {
+        Self {
+            max_width: 16384,
+            max_height: 16384,
+            max_bytes: 32 * 1024 * 1024,
+        }
+    }
+}
+
+/// How [`Client::login`] is retried when it fails because of the network or the server
+///
+/// Login endpoints are sensitive to rate limiting, aggressive retries risk locking the account,
+/// so by default a login is not retried. Wrong credentials are never retried
+#[must_use]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoginRetry {
+    /// Maximum number of attempts, including the first one
+    pub max_attempts: u32,
+    /// Delay before the second attempt, doubled for each further attempt
+    pub backoff: Duration,
+}
+
+impl Default for LoginRetry {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl LoginRetry {
+    /// Run `login` until it succeeds, fails with an error that is not retryable, or the attempts run out
+    pub(crate) async fn run<F, Fut, R>(&self, mut login: F) -> Result<R, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<R, Error>>,
+    {
+        let mut attempt = 1;
+
+        loop {
+            match login().await {
+                Err(error) if error.is_retryable() && attempt < self.max_attempts => {
+                    let delay = self.delay(attempt);
+                    warn!(
+                        "Login failed, retry in {} seconds: {error}",
+                        delay.as_secs()
+                    );
+
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    #[must_use]
+    fn delay(&self, attempt: u32) -> Duration {
+        self.backoff
+            .saturating_mul(2_u32.saturating_pow(attempt.saturating_sub(1)))
+    }
+}
+
+/// Format and dimensions of an image, read from its header
+#[must_use]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageMeta {
+    /// Image format
+    pub format: ImageFormat,
+    /// Image width
+    pub width: u32,
+    /// Image height
+    pub height: u32,
+    /// Size of the encoded image in bytes
+    pub byte_size: usize,
+}
+
+/// A version of the content of a chapter
+#[must_use]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChapterVersion {
+    /// Version id, [`ChapterVersion::CURRENT`] is the current content
+    pub id: u32,
+    /// The time this version was published
+    pub update_time: Option<NaiveDateTime>,
+}
+
+impl ChapterVersion {
+    /// Version id of the current content
+    pub const CURRENT: u32 = 0;
+}
+
 /// Traits that abstract client behavior
 #[async_trait]
 pub trait Client {
     /// set proxy
+    ///
+    /// Of this method and [`Client::no_proxy`], the one called last takes effect
     fn proxy(&mut self, proxy: Url);
 
-    /// Do not use proxy (environment variables used to set proxy are ignored)
+    /// Set the proxy used to download images, e.g. covers and illustrations,
+    /// if it is not set, the proxy set by [`Client::proxy`] is used
+    ///
+    /// It takes precedence over [`Client::no_proxy`], which then only applies to the Api requests
+    fn image_proxy(&mut self, proxy: Url);
+
+    /// Do not use proxy (environment variables used to set proxy are ignored),
+    /// a proxy set by [`Client::proxy`] before is cleared
     fn no_proxy(&mut self);
 
     /// Set the certificate path for use with packet capture tools
+    ///
+    /// The certificate is added as an extra trusted root, it does not change the
+    /// negotiated TLS version, so the packet capture tool must also satisfy
+    /// the version set by [`Client::min_tls_version`]
     fn cert<T>(&mut self, cert_path: T)
     where
         T: AsRef<Path>;
 
+    /// Pin the certificate in DER format, so that the Api only accepts certificate chains issued by it,
+    /// e.g. the root or intermediate CA certificate of the site, to detect a man-in-the-middle
+    ///
+    /// The built-in root certificates are no longer trusted, images are not affected as they are served by CDNs.
+    /// It can not be used together with [`Client::cert`], the first request fails if both are set
+    fn pin_cert(&mut self, der: &[u8]);
+
+    /// Set the minimum TLS version, by default any version supported by rustls is accepted
+    fn min_tls_version(&mut self, version: TlsVersion);
+
+    /// Send the requests with this reqwest client instead of building one, e.g. to use custom DNS or instrumentation
+    ///
+    /// The headers and the cookies of the crate are still added to each request, but [`Client::proxy`],
+    /// [`Client::image_proxy`], [`Client::no_proxy`], [`Client::cert`], [`Client::pin_cert`],
+    /// [`Client::min_tls_version`] and [`Client::pool_idle_timeout`] are ignored,
+    /// configure the supplied client instead. [`Client::reset_connections`] has no effect on it
+    fn reqwest_client(&mut self, client: reqwest::Client);
+
+    /// Close pooled connections that have been idle for longer than `timeout`, by default reqwest closes them after 90 seconds
+    fn pool_idle_timeout(&mut self, timeout: Duration);
+
+    /// Set the base host of the Api, e.g. a local mock server or a mirror, by default the production host is used
+    fn host(&mut self, host: Url);
+
+    /// When downloading an outdated chapter fails because of the network,
+    /// return the outdated content in the cache instead of the error, the default is false
+    fn stale_ok(&mut self, flag: bool);
+
+    /// Record the latency of every request, must be called before the first request
+    fn enable_metrics(&mut self);
+
+    /// Get the request count and latency of each endpoint, empty if metrics are not enabled
+    fn metrics(&self) -> HashMap<String, EndpointMetrics>;
+
+    /// Set the limits used when decoding images, see [`ImageLimits::default`] for the default limits
+    fn image_limits(&mut self, limits: ImageLimits);
+
+    /// Set the maximum number of images decoded at the same time, the default is the number of CPUs
+    ///
+    /// Images are decoded on the blocking thread pool, so that decoding a batch of large images
+    /// does not occupy the worker threads of the async runtime
+    fn max_image_decodes(&mut self, count: usize);
+
+    /// Write downloaded images to the cache in the background instead of waiting for the write,
+    /// the default is false, [`Client::flush`] and [`Client::shutdown`] wait for the pending writes
+    fn write_behind_images(&mut self, flag: bool);
+
+    /// Cache images as files under `dir` instead of in the database, the default is the database
+    ///
+    /// Large image sets make the database file big and slow to back up or vacuum, files avoid that,
+    /// but the images are then not included by [`Client::export_cache`] and are always written immediately
+    fn image_cache_fs(&mut self, dir: PathBuf);
+
+    /// Never read from or write to the cache when getting content and images, they are always downloaded
+    ///
+    /// Unlike [`Client::stale_ok`], nothing is stored, which suits one-off or privacy-sensitive reads,
+    /// explicit calls such as [`Client::cache_content`] still write to the cache
+    fn no_cache(&mut self);
+
+    /// The name of the website, e.g. `sfacg`
+    fn site_name(&self) -> &'static str;
+
+    /// The url of the novel page on the website
+    fn novel_url(&self, id: u32) -> Url;
+
     /// Stop the client, save the data
     async fn shutdown(&self) -> Result<(), Error>;
 
+    /// Wait for all pending cache writes and make them durable
+    async fn flush(&self) -> Result<(), Error>;
+
+    /// Drop all pooled connections by rebuilding the HTTP clients, the cookies and the token are kept
+    ///
+    /// Useful for recovering from stale connections, e.g. after the site restarts
+    async fn reset_connections(&self) -> Result<(), Error>;
+
+    /// Get the space used by the cached texts and images
+    async fn cache_size(&self) -> Result<CacheSize, Error>;
+
+    /// Export the cache database and the config files to a tar archive
+    async fn export_cache(&self, out: &Path) -> Result<(), Error>;
+
+    /// Import the cache database and the config files from an archive created by [`Client::export_cache`]
+    ///
+    /// This must be called before the cache is used, the imported config takes effect the next time the client is created.
+    /// If the local cache is newer than the archive, the import is refused unless `force` is set
+    async fn import_cache(&self, archive: &Path, force: bool) -> Result<(), Error>;
+
     /// Add cookie
     async fn add_cookie(&self, cookie_str: &str, url: &Url) -> Result<(), Error>;
 
+    /// Export the session of the logged-in user as an opaque string,
+    /// so that it can be kept in a shared store instead of the local config files
+    async fn export_session(&self) -> Result<String, Error>;
+
+    /// Restore a session exported by [`Client::export_session`], replacing the current one
+    async fn import_session(&self, session: &str) -> Result<(), Error>;
+
+    /// Login, then save the session in the keyring of the platform under the app name and `username`
+    ///
+    /// The client may still save the session in its config files, see [`crate::CiweimaoClient::persist_token`]
+    async fn login_saving_to_keyring<T, E>(&self, username: T, password: E) -> Result<(), Error>
+    where
+        T: AsRef<str> + Send + Sync,
+        E: AsRef<str> + Send + Sync;
+
+    /// Restore the session saved by [`Client::login_saving_to_keyring`] for `username`
+    async fn login_from_keyring<T>(&self, username: T) -> Result<(), Error>
+    where
+        T: AsRef<str> + Send + Sync;
+
+    /// Set how the login is retried, by default it is not retried
+    ///
+    /// Each attempt repeats the whole login, including the verification, e.g. a new SMS code is requested
+    fn login_retry(&mut self, policy: LoginRetry);
+
     /// Login
     async fn login<T, E>(&self, username: T, password: E) -> Result<(), Error>
     where
@@ -225,23 +752,579 @@ pub trait Client {
     /// Get the information of the logged-in user, if the information fails to get, it will return None
     async fn user_info(&self) -> Result<Option<UserInfo>, Error>;
 
+    /// Get the daily sign-in status of the logged-in user
+    async fn sign_in_status(&self) -> Result<SignInStatus, Error>;
+
+    /// Daily sign-in, return [`Error::AlreadySignedIn`] if the user has already signed in today
+    async fn sign_in(&self) -> Result<(), Error>;
+
     /// Get Novel Information
     async fn novel_info(&self, id: u32) -> Result<Option<NovelInfo>, Error>;
 
+    /// Get author information, the author id is in [`NovelInfo::author_id`]
+    async fn author_info(&self, author_id: u32) -> Result<AuthorInfo, Error>;
+
     /// Get volume Information
     async fn volume_infos(&self, id: u32) -> Result<VolumeInfos, Error>;
 
+    /// Get the chapters of the novel one by one, in order
+    ///
+    /// Both sites list all chapters of a novel in a single response,
+    /// so the chapters are yielded from the volume information once it is fetched
+    fn chapters_stream<'a>(&'a self, id: u32) -> BoxStream<'a, Result<ChapterInfo, Error>>
+    where
+        Self: Sync,
+    {
+        stream::once(self.volume_infos(id))
+            .map_ok(|volume_infos| {
+                stream::iter(
+                    volume_infos
+                        .into_iter()
+                        .flat_map(|volume_info| volume_info.chapter_infos)
+                        .map(Ok),
+                )
+            })
+            .try_flatten()
+            .boxed()
+    }
+
+    /// Get the table of contents of the novel, a compact form of [`Client::volume_infos`]
+    async fn toc(&self, id: u32) -> Result<Toc, Error> {
+        Ok(self
+            .volume_infos(id)
+            .await?
+            .into_iter()
+            .map(|volume_info| {
+                let chapters = volume_info
+                    .chapter_infos
+                    .into_iter()
+                    .map(|chapter_info| (chapter_info.identifier, chapter_info.title))
+                    .collect();
+
+                (volume_info.title, chapters)
+            })
+            .collect())
+    }
+
+    /// Get the novel information and the volume information in one call
+    async fn novel_snapshot(&self, id: u32) -> Result<NovelSnapshot, Error> {
+        let novel_info = self
+            .novel_info(id)
+            .await?
+            .ok_or_else(|| Error::NovelApi(format!("The novel does not exist: `{id}`")))?;
+
+        Ok(NovelSnapshot {
+            novel_info,
+            volume_infos: self.volume_infos(id).await?,
+        })
+    }
+
+    /// Get the number of chapters and locked chapters of the novel
+    async fn novel_accessibility(&self, id: u32) -> Result<NovelAccessibility, Error> {
+        Ok(NovelAccessibility::from(&self.volume_infos(id).await?))
+    }
+
+    /// Can every chapter of the novel be downloaded by the current user
+    async fn novel_fully_accessible(&self, id: u32) -> Result<bool, Error> {
+        Ok(self.novel_accessibility(id).await?.is_fully_accessible())
+    }
+
+    /// Get the identifiers of the VIP chapters of the novel that the logged-in user has purchased
+    ///
+    /// The login state is checked with an extra [`Client::user_info`] request, sent concurrently with
+    /// [`Client::volume_infos`], because sfacg returns the chapter list to anonymous users as well
+    async fn owned_chapters(&self, novel_id: u32) -> Result<HashSet<Identifier>, Error> {
+        let (user_info, volume_infos) =
+            future::try_join(self.user_info(), self.volume_infos(novel_id)).await?;
+        if user_info.is_none() {
+            return Err(Error::NotLoggedIn);
+        }
+
+        Ok(volume_infos
+            .into_iter()
+            .flat_map(|volume_info| volume_info.chapter_infos)
+            .filter(ChapterInfo::is_owned)
+            .map(|chapter_info| chapter_info.identifier)
+            .collect())
+    }
+
+    /// Get the chapters of the novel that can be downloaded by the current account, in order
+    async fn accessible_chapters(&self, id: u32) -> Result<Vec<ChapterInfo>, Error> {
+        Ok(self
+            .volume_infos(id)
+            .await?
+            .into_iter()
+            .flat_map(|volume_info| volume_info.chapter_infos)
+            .filter(ChapterInfo::can_download)
+            .collect())
+    }
+
     /// Get content Information
     async fn content_infos(&self, info: &ChapterInfo) -> Result<ContentInfos, Error>;
 
+    /// Get the full content if the chapter is accessible, otherwise the free preview of the locked chapter,
+    /// `None` if the site returns no preview
+    ///
+    /// Previews are cached apart from the full content, so they never replace it
+    async fn chapter_preview(&self, info: &ChapterInfo) -> Result<Option<ChapterPreview>, Error>;
+
+    /// Get the available versions of the content of the chapter, the current version comes first
+    ///
+    /// The default only returns the current version, for sites without the history of chapters
+    async fn chapter_versions(&self, info: &ChapterInfo) -> Result<Vec<ChapterVersion>, Error> {
+        Ok(vec![ChapterVersion {
+            id: ChapterVersion::CURRENT,
+            update_time: info.update_time,
+        }])
+    }
+
+    /// Get content Information of a version returned by [`Client::chapter_versions`]
+    async fn content_infos_version(
+        &self,
+        info: &ChapterInfo,
+        version_id: u32,
+    ) -> Result<ContentInfos, Error> {
+        if version_id != ChapterVersion::CURRENT {
+            return Err(Error::NovelApi(format!(
+                "The version `{version_id}` of the chapter is not available: `{}`",
+                info.title
+            )));
+        }
+
+        self.content_infos(info).await
+    }
+
+    /// Get content Information of the chapter at the zero-based position in the novel
+    async fn content_by_position(
+        &self,
+        novel_id: u32,
+        volume_idx: usize,
+        chapter_idx: usize,
+    ) -> Result<ContentInfos, Error> {
+        let volume_infos = self.volume_infos(novel_id).await?;
+
+        let volume_info = volume_infos.get(volume_idx).ok_or_else(|| {
+            Error::NovelApi(format!(
+                "Volume index out of range: the novel `{novel_id}` has {} volumes, but the index is {volume_idx}",
+                volume_infos.len()
+            ))
+        })?;
+
+        let chapter_info = volume_info.chapter_infos.get(chapter_idx).ok_or_else(|| {
+            Error::NovelApi(format!(
+                "Chapter index out of range: the volume `{}` has {} chapters, but the index is {chapter_idx}",
+                volume_info.title,
+                volume_info.chapter_infos.len()
+            ))
+        })?;
+
+        self.content_infos(chapter_info).await
+    }
+
+    /// Get content Information of the chapters in the zero-based `range` of all chapters of the novel,
+    /// downloading up to `concurrency` chapters at the same time, the result is in reading order
+    ///
+    /// The range is clamped to the chapters of the novel, so a range past the end gives fewer or no chapters
+    async fn content_infos_range(
+        &self,
+        id: u32,
+        range: Range<usize>,
+        concurrency: usize,
+    ) -> Result<Vec<(ChapterInfo, ContentInfos)>, Error> {
+        let chapter_infos = self
+            .volume_infos(id)
+            .await?
+            .into_iter()
+            .flat_map(|volume_info| volume_info.chapter_infos)
+            .collect::<Vec<_>>();
+
+        let end = range.end.min(chapter_infos.len());
+        let start = range.start.min(end);
+
+        let tasks = chapter_infos
+            .into_iter()
+            .skip(start)
+            .take(end - start)
+            .map(|info| async move {
+                let content_infos = self.content_infos(&info).await?;
+                Ok::<_, Error>((info, content_infos))
+            })
+            .collect::<Vec<_>>();
+
+        stream::iter(tasks)
+            .buffered(concurrency.max(1))
+            .try_collect()
+            .await
+    }
+
+    /// Get content Information, return the text lines and the image urls separately, both in reading order
+    async fn content_infos_split(
+        &self,
+        info: &ChapterInfo,
+    ) -> Result<(Vec<String>, Vec<Url>), Error> {
+        let mut texts = Vec::new();
+        let mut image_urls = Vec::new();
+
+        for content_info in self.content_infos(info).await? {
+            match content_info {
+                ContentInfo::Text(text) => texts.push(text),
+                ContentInfo::Image(url) => image_urls.push(url),
+            }
+        }
+
+        Ok((texts, image_urls))
+    }
+
+    /// Download the content of the chapters concurrently, the results are yielded in the order of `infos`
+    ///
+    /// A completed chapter is held back until all chapters before it have been yielded
+    fn download_ordered<'a, I>(
+        &'a self,
+        infos: I,
+        concurrency: usize,
+    ) -> BoxStream<'a, Result<ContentInfos, Error>>
+    where
+        Self: Sync,
+        I: IntoIterator<Item = &'a ChapterInfo>,
+        I::IntoIter: Send + 'a,
+    {
+        stream::iter(infos)
+            .map(move |info| self.content_infos(info))
+            .buffered(concurrency.max(1))
+            .boxed()
+    }
+
+    /// Download all chapters of the novel concurrently and write them to `out` as plain text in order,
+    /// with the volume and chapter titles as headers
+    ///
+    /// Locked chapters are written as a placeholder, images are written as their url
+    async fn export_txt<W>(&self, id: u32, mut out: W, concurrency: usize) -> Result<(), Error>
+    where
+        Self: Sync,
+        W: Write + Send,
+    {
+        let volume_infos = self.volume_infos(id).await?;
+
+        let mut contents = self.download_ordered(
+            volume_infos
+                .iter()
+                .flat_map(|volume_info| &volume_info.chapter_infos)
+                .filter(|info| info.can_download()),
+            concurrency,
+        );
+
+        for volume_info in &volume_infos {
+            writeln!(out, "{}\n", volume_info.title)?;
+
+            for info in &volume_info.chapter_infos {
+                writeln!(out, "{}\n", info.title)?;
+
+                let content_infos = if info.can_download() {
+                    match contents.next().await {
+                        Some(content_infos) => content_infos,
+                        None => Err(Error::NovelApi(format!(
+                            "The content of the chapter was not downloaded: `{}`",
+                            info.title
+                        ))),
+                    }
+                } else {
+                    Err(Error::ChapterLocked(info.title.clone()))
+                };
+
+                match content_infos {
+                    Ok(content_infos) => {
+                        for content_info in content_infos {
+                            match content_info {
+                                ContentInfo::Text(text) => writeln!(out, "{text}")?,
+                                ContentInfo::Image(url) => writeln!(out, "[Image: {url}]")?,
+                            }
+                        }
+                    }
+                    Err(Error::ChapterLocked(_)) => writeln!(out, "[This chapter is locked]")?,
+                    Err(error) => return Err(error),
+                }
+
+                writeln!(out)?;
+            }
+        }
+
+        Ok(out.flush()?)
+    }
+
+    /// Is the content of the chapter in the cache and up to date
+    async fn is_content_cached(&self, info: &ChapterInfo) -> Result<bool, Error>;
+
+    /// Compare the word count of the cached text with the word count of the chapter given by [`Client::volume_infos`]
+    async fn verify_cached_chapter(&self, info: &ChapterInfo) -> Result<CacheVerdict, Error>;
+
+    /// Download the chapters of the novel that are missing from the cache or outdated
+    async fn refresh_novel(&self, id: u32, concurrency: usize) -> Result<RefreshReport, Error> {
+        let volume_infos = self.volume_infos(id).await?;
+
+        let mut report = RefreshReport::default();
+        let mut outdated = Vec::new();
+
+        for info in volume_infos
+            .iter()
+            .flat_map(|volume_info| &volume_info.chapter_infos)
+        {
+            if !info.can_download() || self.is_content_cached(info).await? {
+                report.skipped += 1;
+            } else {
+                outdated.push(info);
+            }
+        }
+
+        let tasks = outdated
+            .into_iter()
+            .map(|info| async move { (info, self.content_infos(info).await) })
+            .collect::<Vec<_>>();
+        let mut results = stream::iter(tasks).buffer_unordered(concurrency.max(1));
+
+        while let Some((info, result)) = results.next().await {
+            match result {
+                Ok(_) => report.fetched += 1,
+                Err(error) => {
+                    error!("Failed to download chapter: `{}`, {error}", info.title);
+                    report.failed += 1;
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Delete the cached text of all chapters of the novel, return the number of chapters deleted
+    ///
+    /// The chapters are obtained by [`Client::volume_infos`], if it fails,
+    /// use [`Client::clear_chapters_cache`] with the identifiers of the chapters instead
+    async fn clear_novel_cache(&self, novel_id: u32) -> Result<u64, Error> {
+        let identifiers = self
+            .volume_infos(novel_id)
+            .await?
+            .into_iter()
+            .flat_map(|volume_info| volume_info.chapter_infos)
+            .map(|chapter_info| chapter_info.identifier)
+            .collect::<Vec<_>>();
+
+        self.clear_chapters_cache(&identifiers).await
+    }
+
+    /// Delete the cached text of the chapters, return the number of chapters deleted
+    async fn clear_chapters_cache(&self, identifiers: &[Identifier]) -> Result<u64, Error>;
+
+    /// Save the text of a chapter obtained by other means to the cache
+    async fn cache_content(&self, info: &ChapterInfo, text: &str) -> Result<(), Error>;
+
+    /// Save an image obtained by other means to the cache
+    async fn cache_image(&self, url: &Url, bytes: &[u8]) -> Result<(), Error>;
+
     /// Download image
     async fn image(&self, url: &Url) -> Result<DynamicImage, Error>;
 
+    /// Download image, return the bytes without decoding
+    async fn image_bytes(&self, url: &Url) -> Result<Vec<u8>, Error>;
+
+    /// Get the format and dimensions of the image without decoding it, the bytes come from the cache
+    async fn image_meta(&self, url: &Url) -> Result<ImageMeta, Error> {
+        crate::image_meta(&self.image_bytes(url).await?)
+    }
+
+    /// Save the cover of the novel to `path` as the original bytes, without decoding and re-encoding, the bytes come from the cache
+    ///
+    /// If `path` has no extension, the extension of the detected image format is added
+    async fn save_cover(&self, novel: &NovelInfo, path: &Path) -> Result<(), Error> {
+        let cover_url = novel
+            .cover_url
+            .as_ref()
+            .ok_or_else(|| Error::NovelApi(format!("The novel has no cover: `{}`", novel.name)))?;
+
+        let bytes = self.image_bytes(cover_url).await?;
+
+        let path = match path.extension() {
+            Some(_) => path.to_path_buf(),
+            None => path.with_extension(image::guess_format(&bytes)?.extensions_str()[0]),
+        };
+        fs::write(path, bytes).await?;
+
+        Ok(())
+    }
+
+    /// Get the content of the chapter with the images downloaded concurrently, through the cache
+    async fn content_infos_with_images(
+        &self,
+        info: &ChapterInfo,
+        concurrency: usize,
+    ) -> Result<Vec<ContentInfoResolved>, Error> {
+        let tasks = self
+            .content_infos(info)
+            .await?
+            .into_iter()
+            .map(|content_info| async move {
+                match content_info {
+                    ContentInfo::Text(text) => Ok(ContentInfoResolved::Text(text)),
+                    ContentInfo::Image(url) => {
+                        Ok(ContentInfoResolved::Image(self.image(&url).await?))
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+
+        stream::iter(tasks)
+            .buffered(concurrency.max(1))
+            .try_collect()
+            .await
+    }
+
+    /// Download the covers of multiple novels concurrently, return a map of novel id to cover
+    ///
+    /// Novels without a cover url are skipped, covers that fail to download are logged and omitted
+    async fn covers(
+        &self,
+        novels: &[&NovelInfo],
+        concurrency: usize,
+    ) -> Result<HashMap<u32, DynamicImage>, Error> {
+        let tasks = novels
+            .iter()
+            .filter_map(|novel_info| {
+                novel_info
+                    .cover_url
+                    .as_ref()
+                    .map(|cover_url| (novel_info.id, cover_url))
+            })
+            .map(|(id, cover_url)| async move { (id, cover_url, self.image(cover_url).await) })
+            .collect::<Vec<_>>();
+        let mut covers = stream::iter(tasks).buffer_unordered(concurrency.max(1));
+
+        let mut result = HashMap::new();
+        while let Some((id, cover_url, image)) = covers.next().await {
+            match image {
+                Ok(image) => {
+                    result.insert(id, image);
+                }
+                Err(error) => error!("Failed to download cover: `{cover_url}`, {error}"),
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Get the novel information and cover of multiple novels concurrently, keeping the order of `ids`
+    ///
+    /// Novels that do not exist are skipped, covers that fail to download are logged and left empty
+    async fn novel_cards(&self, ids: &[u32], concurrency: usize) -> Result<Vec<NovelCard>, Error> {
+        let tasks = ids
+            .iter()
+            .map(|id| async move {
+                let info = match self.novel_info(*id).await? {
+                    Some(info) => info,
+                    None => return Ok::<_, Error>(None),
+                };
+
+                let cover = match &info.cover_url {
+                    Some(cover_url) => match self.image(cover_url).await {
+                        Ok(cover) => Some(cover),
+                        Err(error) => {
+                            error!("Failed to download cover: `{cover_url}`, {error}");
+                            None
+                        }
+                    },
+                    None => None,
+                };
+
+                Ok(Some(NovelCard { info, cover }))
+            })
+            .collect::<Vec<_>>();
+
+        let cards: Vec<Option<NovelCard>> = stream::iter(tasks)
+            .buffered(concurrency.max(1))
+            .try_collect()
+            .await?;
+
+        Ok(cards.into_iter().flatten().collect())
+    }
+
     /// Search, return novel id
+    ///
+    /// A `size` of 0 means [`DEFAULT_PAGE_SIZE`], sizes above the maximum of the site are clamped
     async fn search_infos<T>(&self, text: T, page: u16, size: u16) -> Result<Vec<u32>, Error>
     where
         T: AsRef<str> + Send + Sync;
 
+    /// Search up to `max_results` novels, requesting as many pages as needed, return novel id
+    ///
+    /// Novels that appear on more than one page are only returned once
+    async fn search_all<T>(&self, text: T, max_results: usize) -> Result<Vec<u32>, Error>
+    where
+        T: AsRef<str> + Send + Sync,
+    {
+        const PAGE_SIZE: u16 = 20;
+
+        let mut result = Vec::new();
+        let mut ids = HashSet::new();
+        let mut page = 0;
+
+        while result.len() < max_results {
+            let page_ids = self.search_infos(text.as_ref(), page, PAGE_SIZE).await?;
+            let page_len = page_ids.len();
+
+            let old_len = result.len();
+            for id in page_ids {
+                if ids.insert(id) {
+                    result.push(id);
+                }
+            }
+
+            // Stop when the results run out, or a page only repeats the previous ones
+            if page_len < PAGE_SIZE as usize || result.len() == old_len {
+                break;
+            }
+            page += 1;
+        }
+
+        result.truncate(max_results);
+        Ok(result)
+    }
+
+    /// Search within a category, return novel id
+    ///
+    /// If the site does not support searching within a category, the search results are filtered
+    /// by their novel information, so a page of N results costs N + 1 requests,
+    /// of which up to `concurrency` [`Client::novel_info`] requests are sent at the same time
+    async fn search_infos_in<T>(
+        &self,
+        text: T,
+        category: &Category,
+        page: u16,
+        size: u16,
+        concurrency: usize,
+    ) -> Result<Vec<u32>, Error>
+    where
+        T: AsRef<str> + Send + Sync,
+    {
+        let tasks = self
+            .search_infos(text, page, size)
+            .await?
+            .into_iter()
+            .map(|id| async move { Ok::<_, Error>((id, self.novel_info(id).await?)) })
+            .collect::<Vec<_>>();
+
+        let novel_infos: Vec<_> = stream::iter(tasks)
+            .buffered(concurrency.max(1))
+            .try_collect()
+            .await?;
+
+        Ok(novel_infos
+            .into_iter()
+            .filter(|(_, novel_info)| {
+                crate::is_some_and(novel_info.as_ref(), |novel_info| {
+                    novel_info.category.as_ref() == Some(category)
+                })
+            })
+            .map(|(id, _)| id)
+            .collect())
+    }
+
     /// Get the favorite novel of the logged-in user and return the novel id
     async fn bookshelf_infos(&self) -> Result<Vec<u32>, Error>;
 
@@ -251,6 +1334,240 @@ pub trait Client {
     /// Get all tags
     async fn tags(&self) -> Result<&Vec<Tag>, Error>;
 
+    /// Get the tags of novels in the category, sites without category specific tags return all tags
+    async fn tags_for_category(&self, _category: &Category) -> Result<Vec<Tag>, Error> {
+        Ok(self.tags().await?.clone())
+    }
+
+    /// Get all categories with the tags of each category, the tags are fetched concurrently
+    async fn browse_taxonomy(&self) -> Result<&Vec<(Category, Vec<Tag>)>, Error>;
+
+    /// Fetch all categories and tags in advance, so that errors surface here
+    /// rather than in the first call that needs them
+    async fn prewarm(&self) -> Result<(), Error> {
+        self.categories().await?;
+        self.tags().await?;
+
+        Ok(())
+    }
+
     /// Search all matching novels
-    async fn novels(&self, option: &Options, page: u16, size: u16) -> Result<Vec<u32>, Error>;
+    ///
+    /// A `size` of 0 means [`DEFAULT_PAGE_SIZE`], sizes above the maximum of the site are clamped
+    async fn novels(&self, option: &Options, page: u16, size: u16) -> Result<Vec<u32>, Error> {
+        Ok(self.novels_page(option, page, size).await?.ids)
+    }
+
+    /// Search all matching novels, return the novel id of the page and the total number of matches
+    async fn novels_page(
+        &self,
+        option: &Options,
+        page: u16,
+        size: u16,
+    ) -> Result<NovelsPage, Error>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn novel_accessibility() -> Result<(), Error> {
+        let volume_infos = vec![
+            VolumeInfo {
+                title: String::from("volume 1"),
+                description: None,
+                cover_url: None,
+                chapter_infos: vec![
+                    ChapterInfo {
+                        is_accessible: Some(true),
+                        ..Default::default()
+                    },
+                    ChapterInfo {
+                        is_accessible: Some(false),
+                        ..Default::default()
+                    },
+                ],
+            },
+            VolumeInfo {
+                title: String::from("volume 2"),
+                description: None,
+                cover_url: None,
+                chapter_infos: vec![ChapterInfo {
+                    is_valid: Some(false),
+                    ..Default::default()
+                }],
+            },
+        ];
+
+        let accessibility = NovelAccessibility::from(&volume_infos);
+        assert_eq!(
+            accessibility,
+            NovelAccessibility {
+                chapter_count: 3,
+                locked_chapter_count: 2,
+            }
+        );
+        assert!(!accessibility.is_fully_accessible());
+
+        assert!(NovelAccessibility::from(&VolumeInfos::new()).is_fully_accessible());
+
+        Ok(())
+    }
+
+    #[test]
+    fn is_owned() -> Result<(), Error> {
+        let chapter_info = ChapterInfo {
+            is_vip: Some(true),
+            is_accessible: Some(true),
+            ..Default::default()
+        };
+        assert!(chapter_info.is_owned());
+
+        let chapter_info = ChapterInfo {
+            is_vip: Some(true),
+            is_accessible: Some(false),
+            ..Default::default()
+        };
+        assert!(!chapter_info.is_owned());
+
+        let chapter_info = ChapterInfo {
+            is_vip: Some(false),
+            is_accessible: Some(true),
+            ..Default::default()
+        };
+        assert!(!chapter_info.is_owned());
+
+        Ok(())
+    }
+
+    #[test]
+    fn novel_snapshot() -> Result<(), Error> {
+        let snapshot = NovelSnapshot {
+            novel_info: NovelInfo {
+                id: 1,
+                name: String::from("novel"),
+                ..Default::default()
+            },
+            volume_infos: vec![VolumeInfo {
+                title: String::from("volume 1"),
+                description: None,
+                cover_url: None,
+                chapter_infos: vec![ChapterInfo {
+                    identifier: Identifier::Id(2),
+                    title: String::from("chapter 1"),
+                    ..Default::default()
+                }],
+            }],
+        };
+
+        let json = serde_json::to_string(&snapshot)?;
+        let snapshot: NovelSnapshot = serde_json::from_str(&json)?;
+
+        assert_eq!(snapshot.novel_info.name, "novel");
+        assert_eq!(snapshot.volume_infos[0].chapter_infos[0].title, "chapter 1");
+        assert_eq!(json, serde_json::to_string(&snapshot)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn check_tag_logic() -> Result<(), Error> {
+        let tag = |name: &str| Tag {
+            id: None,
+            name: name.to_string(),
+        };
+
+        let options = Options {
+            tags: Some(vec![tag("百合")]),
+            tag_logic: TagLogic::Or,
+            ..Default::default()
+        };
+        assert!(options.check_tag_logic(TagLogic::And).is_ok());
+
+        let options = Options {
+            tags: Some(vec![tag("百合"), tag("日常")]),
+            ..options
+        };
+        assert!(options.check_tag_logic(TagLogic::Or).is_ok());
+        assert!(matches!(
+            options.check_tag_logic(TagLogic::And),
+            Err(Error::NovelApi(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn identifier() -> Result<(), Error> {
+        let identifiers = [
+            Identifier::Id(1),
+            Identifier::Url(Url::parse("https://www.ciweimao.com/chapter/1")?),
+        ];
+
+        for identifier in identifiers {
+            assert_eq!(identifier.to_string().parse::<Identifier>()?, identifier);
+        }
+
+        assert!("not an identifier".parse::<Identifier>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn page_size() {
+        assert_eq!(super::page_size(0, 50), DEFAULT_PAGE_SIZE);
+        assert_eq!(super::page_size(20, 50), 20);
+        assert_eq!(super::page_size(10000, 50), 50);
+    }
+
+    #[tokio::test]
+    async fn login_retry() -> Result<(), Error> {
+        let policy = LoginRetry {
+            max_attempts: 3,
+            backoff: Duration::from_millis(1),
+        };
+        assert_eq!(policy.delay(1), Duration::from_millis(1));
+        assert_eq!(policy.delay(3), Duration::from_millis(4));
+
+        let server_error = || Error::Http {
+            code: http::StatusCode::INTERNAL_SERVER_ERROR,
+            msg: String::default(),
+            headers: http::HeaderMap::new(),
+        };
+
+        let mut attempts = 0;
+        let result = policy
+            .run(|| {
+                attempts += 1;
+                async { Err::<(), _>(server_error()) }
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts, 3);
+
+        let mut attempts = 0;
+        let result = policy
+            .run(|| {
+                attempts += 1;
+                async { Err::<(), _>(Error::InvalidCredentials(String::default())) }
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+
+        let mut attempts = 0;
+        let result = LoginRetry::default()
+            .run(|| {
+                attempts += 1;
+                async { Err::<(), _>(server_error()) }
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+
+        Ok(())
+    }
 }
@@ -1,5 +1,11 @@
 pub mod image;
+pub mod metadata;
+pub mod preview;
 pub mod text;
+pub mod version;
 
 pub use self::image::Entity as Image;
+pub use self::metadata::Entity as Metadata;
+pub use self::preview::Entity as Preview;
 pub use self::text::Entity as Text;
+pub use self::version::Entity as Version;
@@ -0,0 +1,16 @@
+use chrono::NaiveDateTime;
+use sea_orm::entity::prelude::*;
+
+#[derive(Debug, PartialEq, Eq, Clone, DeriveEntityModel)]
+#[sea_orm(table_name = "preview")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub identifier: String,
+    pub date_time: Option<NaiveDateTime>,
+    pub text: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,47 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+
+#[must_use]
+#[derive(Iden)]
+enum Preview {
+    Table,
+    Identifier,
+    DateTime,
+    Text,
+}
+
+#[must_use]
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Preview::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Preview::Identifier)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Preview::DateTime).date_time())
+                    .col(ColumnDef::new(Preview::Text).binary().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Preview::Table).if_exists().to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+use async_trait::async_trait;
+use sea_orm_migration::prelude::*;
+
+#[must_use]
+#[derive(Iden)]
+enum Version {
+    Table,
+    Id,
+    Identifier,
+    DateTime,
+    Text,
+}
+
+#[must_use]
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Version::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Version::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Version::Identifier).string().not_null())
+                    .col(ColumnDef::new(Version::DateTime).date_time())
+                    .col(ColumnDef::new(Version::Text).binary().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-version-identifier")
+                    .table(Version::Table)
+                    .col(Version::Identifier)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Version::Table).if_exists().to_owned())
+            .await?;
+
+        Ok(())
+    }
+}
@@ -1,4 +1,7 @@
 mod m20221215_070928_create_table;
+mod m20261018_023500_create_preview_table;
+mod m20261018_120000_create_metadata_table;
+mod m20261018_130000_create_version_table;
 
 use async_trait::async_trait;
 pub use sea_orm_migration::prelude::*;
@@ -9,6 +12,11 @@ pub struct Migrator;
 #[async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20221215_070928_create_table::Migration)]
+        vec![
+            Box::new(m20221215_070928_create_table::Migration),
+            Box::new(m20261018_023500_create_preview_table::Migration),
+            Box::new(m20261018_120000_create_metadata_table::Migration),
+            Box::new(m20261018_130000_create_version_table::Migration),
+        ]
     }
 }
@@ -1,25 +1,43 @@
 mod entity;
 mod migration;
 
-use std::{io::Cursor, path::PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use async_compression::tokio::{bufread::ZstdDecoder, write::ZstdEncoder};
-use image::{io::Reader, DynamicImage};
-use sea_orm::{ActiveModelTrait, Database, DatabaseConnection, EntityTrait};
+use chrono::NaiveDateTime;
+use sea_orm::{
+    sea_query::OnConflict, ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseBackend,
+    DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, SqlxSqliteConnector, Statement,
+};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous};
 use tokio::{
     fs,
     io::{AsyncReadExt, AsyncWriteExt, BufReader},
+    sync::RwLock,
 };
-use tracing::info;
+use tracing::{error, info};
 use url::Url;
 
-use crate::{ChapterInfo, Error};
-use entity::{Image, Text};
+use crate::{CacheSize, ChapterInfo, Error, Identifier};
+use entity::{Image, Metadata, Preview, Text, Version};
 use migration::{Migrator, MigratorTrait};
 
 #[must_use]
+#[derive(Clone)]
 pub(crate) struct NovelDB {
     db: DatabaseConnection,
+    // Writes hold a read lock, so that `flush` can wait for all of them by taking the write lock
+    writes: Arc<RwLock<()>>,
+    // The Unix timestamp in milliseconds of the last write not yet recorded in the database, 0 if none,
+    // it is recorded by `flush` so that writes do not pay for it
+    last_write: Arc<AtomicI64>,
 }
 
 #[must_use]
@@ -30,16 +48,15 @@ pub(crate) enum FindTextResult {
     Outdate,
 }
 
-#[must_use]
-#[derive(Debug, PartialEq)]
-pub(crate) enum FindImageResult {
-    Ok(DynamicImage),
-    None,
-}
-
 impl NovelDB {
     const DB_NAME: &str = "novel.db";
 
+    /// The key of the metadata row holding the Unix timestamp in milliseconds of the last write
+    const LAST_WRITE: &str = "last_write";
+
+    const MAX_CONNECTIONS: u32 = 8;
+    const BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+
     pub(crate) async fn new(app_name: &str) -> Result<Self, Error> {
         let db_path = NovelDB::db_path(app_name)?;
 
@@ -54,11 +71,101 @@ impl NovelDB {
             fs::create_dir_all(db_path.parent().unwrap()).await?;
         }
 
-        let db_url = format!("sqlite:{}?mode=rwc", db_path.display());
-        let db = Database::connect(db_url).await?;
+        let db = NovelDB::connect(&db_path, true).await?;
         Migrator::up(&db, None).await?;
 
-        Ok(Self { db })
+        Ok(Self {
+            db,
+            writes: Arc::new(RwLock::new(())),
+            last_write: Arc::new(AtomicI64::new(0)),
+        })
+    }
+
+    /// Create a database in a new file under the temporary directory, so that tests never touch the real cache
+    #[cfg(test)]
+    pub(crate) async fn temp() -> Result<Self, Error> {
+        let db_path =
+            std::env::temp_dir().join(format!("novel-api-test-{}.db", uuid::Uuid::new_v4()));
+
+        let db = NovelDB::connect(&db_path, true).await?;
+        Migrator::up(&db, None).await?;
+
+        Ok(Self {
+            db,
+            writes: Arc::new(RwLock::new(())),
+            last_write: Arc::new(AtomicI64::new(0)),
+        })
+    }
+
+    /// Open an existing database file without running migrations
+    pub(crate) async fn open<T>(db_path: T) -> Result<Self, Error>
+    where
+        T: AsRef<Path>,
+    {
+        Ok(Self {
+            db: NovelDB::connect(db_path.as_ref(), false).await?,
+            writes: Arc::new(RwLock::new(())),
+            last_write: Arc::new(AtomicI64::new(0)),
+        })
+    }
+
+    /// Record the time of the last write and close all connections, so that the file can be replaced
+    pub(crate) async fn close(self) -> Result<(), Error> {
+        self.record_last_write().await?;
+        Ok(self.db.close().await?)
+    }
+
+    async fn connect(db_path: &Path, create_if_missing: bool) -> Result<DatabaseConnection, Error> {
+        // WAL mode allows reading while writing, the busy timeout makes
+        // concurrent writers wait for the lock instead of failing with `database is locked`
+        let options = SqliteConnectOptions::new()
+            .filename(db_path)
+            .create_if_missing(create_if_missing)
+            .journal_mode(SqliteJournalMode::Wal)
+            .synchronous(SqliteSynchronous::Normal)
+            .busy_timeout(NovelDB::BUSY_TIMEOUT);
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(NovelDB::MAX_CONNECTIONS)
+            .connect_with(options)
+            .await
+            .map_err(sea_orm::sqlx_error_to_conn_err)?;
+
+        Ok(SqlxSqliteConnector::from_sqlx_sqlite_pool(pool))
+    }
+
+    /// The name of the last migration applied to the database, `None` if no migration has been applied
+    ///
+    /// The migration table is only read, unlike `Migrator::get_migration_models`, which creates it if it is missing
+    pub(crate) async fn schema_version(&self) -> Result<Option<String>, Error> {
+        let table = self
+            .db
+            .query_one(Statement::from_string(
+                DatabaseBackend::Sqlite,
+                "SELECT name FROM sqlite_master WHERE type = 'table' AND name = 'seaql_migrations'"
+                    .to_string(),
+            ))
+            .await?;
+        if table.is_none() {
+            return Ok(None);
+        }
+
+        let row = self
+            .db
+            .query_one(Statement::from_string(
+                DatabaseBackend::Sqlite,
+                "SELECT version FROM seaql_migrations ORDER BY version DESC LIMIT 1".to_string(),
+            ))
+            .await?;
+
+        match row {
+            Some(row) => Ok(Some(row.try_get("", "version")?)),
+            None => Ok(None),
+        }
+    }
+
+    pub(crate) async fn migrate(&self) -> Result<(), Error> {
+        Ok(Migrator::up(&self.db, None).await?)
     }
 
     #[cfg(test)]
@@ -90,10 +197,28 @@ impl NovelDB {
         }
     }
 
+    /// Find the text regardless of whether it is outdated
+    pub(crate) async fn find_stale_text(
+        &self,
+        info: &ChapterInfo,
+    ) -> Result<Option<String>, Error> {
+        match Text::find_by_id(info.identifier.to_string())
+            .one(&self.db)
+            .await?
+        {
+            Some(model) => Ok(Some(unsafe {
+                String::from_utf8_unchecked(zstd_decompress(&model.text).await?)
+            })),
+            None => Ok(None),
+        }
+    }
+
     pub(crate) async fn insert_text<T>(&self, info: &ChapterInfo, text: T) -> Result<(), Error>
     where
         T: AsRef<str>,
     {
+        let _guard = self.writes.read().await;
+
         let model = entity::text::ActiveModel {
             identifier: sea_orm::Set(info.identifier.to_string()),
             date_time: sea_orm::Set(info.update_time),
@@ -101,6 +226,8 @@ impl NovelDB {
         };
         model.insert(&self.db).await?;
 
+        self.mark_written();
+
         Ok(())
     }
 
@@ -108,33 +235,239 @@ impl NovelDB {
     where
         T: AsRef<str>,
     {
+        let _guard = self.writes.read().await;
+
+        let identifier = info.identifier.to_string();
+        let text = text.as_ref().as_bytes();
+
+        // If the text has not changed, only update the time to avoid rewriting the blob
+        let unchanged = match Text::find_by_id(identifier.clone()).one(&self.db).await? {
+            Some(model) => zstd_decompress(&model.text).await? == text,
+            None => false,
+        };
+
         let model = entity::text::ActiveModel {
-            identifier: sea_orm::Set(info.identifier.to_string()),
+            identifier: sea_orm::Set(identifier),
             date_time: sea_orm::Set(info.update_time),
-            text: sea_orm::Set(zstd_compress(text.as_ref().as_bytes()).await?),
+            text: if unchanged {
+                sea_orm::NotSet
+            } else {
+                sea_orm::Set(zstd_compress(text).await?)
+            },
         };
         model.update(&self.db).await?;
 
+        if !unchanged {
+            self.mark_written();
+        }
+
         Ok(())
     }
 
-    pub(crate) async fn find_image(&self, url: &Url) -> Result<FindImageResult, Error> {
-        let model = Image::find_by_id(url.to_string()).one(&self.db).await?;
+    /// Insert or update the text, depending on whether it is already cached
+    pub(crate) async fn save_text<T>(&self, info: &ChapterInfo, text: T) -> Result<(), Error>
+    where
+        T: AsRef<str>,
+    {
+        let text = text.as_ref();
+        if text.trim().is_empty() {
+            return Err(Error::NovelApi(format!(
+                "The text to be cached is empty: `{}`",
+                info.identifier
+            )));
+        }
+
+        match self.find_text(info).await? {
+            FindTextResult::None => self.insert_text(info, text).await,
+            FindTextResult::Ok(_) | FindTextResult::Outdate => self.update_text(info, text).await,
+        }
+    }
+
+    /// Delete the texts of the chapters, return the number of texts deleted
+    pub(crate) async fn delete_texts(&self, identifiers: &[Identifier]) -> Result<u64, Error> {
+        // Stay below the limit of SQLite on the number of variables in a statement
+        const CHUNK_SIZE: usize = 500;
+
+        let _guard = self.writes.read().await;
+
+        let mut deleted = 0;
+        for chunk in identifiers.chunks(CHUNK_SIZE) {
+            deleted += Text::delete_many()
+                .filter(
+                    entity::text::Column::Identifier
+                        .is_in(chunk.iter().map(|identifier| identifier.to_string())),
+                )
+                .exec(&self.db)
+                .await?
+                .rows_affected;
+
+            Version::delete_many()
+                .filter(
+                    entity::version::Column::Identifier
+                        .is_in(chunk.iter().map(|identifier| identifier.to_string())),
+                )
+                .exec(&self.db)
+                .await?;
+        }
+
+        if deleted > 0 {
+            self.mark_written();
+        }
+
+        Ok(deleted)
+    }
+
+    /// Keep the cached text of the chapter as an earlier version, if `text` is about to replace it with different content,
+    /// return whether a version has been kept
+    pub(crate) async fn keep_version<T>(&self, info: &ChapterInfo, text: T) -> Result<bool, Error>
+    where
+        T: AsRef<str>,
+    {
+        let _guard = self.writes.read().await;
+
+        let model = match Text::find_by_id(info.identifier.to_string())
+            .one(&self.db)
+            .await?
+        {
+            Some(model) => model,
+            None => return Ok(false),
+        };
+        if zstd_decompress(&model.text).await? == text.as_ref().as_bytes() {
+            return Ok(false);
+        }
+
+        let version = entity::version::ActiveModel {
+            id: sea_orm::NotSet,
+            identifier: sea_orm::Set(model.identifier),
+            date_time: sea_orm::Set(model.date_time),
+            text: sea_orm::Set(model.text),
+        };
+        version.insert(&self.db).await?;
+        self.mark_written();
+
+        Ok(true)
+    }
+
+    /// The ids and update times of the earlier versions of the chapter, the newest first
+    pub(crate) async fn find_versions(
+        &self,
+        info: &ChapterInfo,
+    ) -> Result<Vec<(u32, Option<NaiveDateTime>)>, Error> {
+        let models = Version::find()
+            .filter(entity::version::Column::Identifier.eq(info.identifier.to_string()))
+            .order_by_desc(entity::version::Column::Id)
+            .all(&self.db)
+            .await?;
+
+        Ok(models
+            .into_iter()
+            .map(|model| (model.id as u32, model.date_time))
+            .collect())
+    }
+
+    /// Find the text of an earlier version of the chapter, `None` if there is no such version
+    pub(crate) async fn find_version(
+        &self,
+        info: &ChapterInfo,
+        id: u32,
+    ) -> Result<Option<String>, Error> {
+        let model = Version::find_by_id(id as i32)
+            .filter(entity::version::Column::Identifier.eq(info.identifier.to_string()))
+            .one(&self.db)
+            .await?;
 
         match model {
+            Some(model) => Ok(Some(unsafe {
+                String::from_utf8_unchecked(zstd_decompress(&model.text).await?)
+            })),
+            None => Ok(None),
+        }
+    }
+
+    /// Find the preview of a locked chapter, `None` if it is not cached or is outdated
+    pub(crate) async fn find_preview(&self, info: &ChapterInfo) -> Result<Option<String>, Error> {
+        match Preview::find_by_id(info.identifier.to_string())
+            .one(&self.db)
+            .await?
+        {
             Some(model) => {
-                let bytes = zstd_decompress(&model.image).await?;
-                let image = Reader::new(Cursor::new(bytes))
-                    .with_guessed_format()?
-                    .decode()?;
+                if crate::is_some_and(model.date_time.zip(info.update_time), |(saved, time)| {
+                    saved < time
+                }) {
+                    return Ok(None);
+                }
 
-                Ok(FindImageResult::Ok(image))
+                Ok(Some(unsafe {
+                    String::from_utf8_unchecked(zstd_decompress(&model.text).await?)
+                }))
             }
-            None => Ok(FindImageResult::None),
+            None => Ok(None),
+        }
+    }
+
+    /// Insert or update the preview of a locked chapter, it is kept apart from the texts
+    /// so that it is never returned as the content of the chapter
+    pub(crate) async fn save_preview<T>(&self, info: &ChapterInfo, text: T) -> Result<(), Error>
+    where
+        T: AsRef<str>,
+    {
+        let _guard = self.writes.read().await;
+
+        let identifier = info.identifier.to_string();
+        let exists = Preview::find_by_id(identifier.clone())
+            .one(&self.db)
+            .await?
+            .is_some();
+
+        let model = entity::preview::ActiveModel {
+            identifier: sea_orm::Set(identifier),
+            date_time: sea_orm::Set(info.update_time),
+            text: sea_orm::Set(zstd_compress(text.as_ref().as_bytes()).await?),
+        };
+        if exists {
+            model.update(&self.db).await?;
+        } else {
+            model.insert(&self.db).await?;
+        }
+
+        self.mark_written();
+
+        Ok(())
+    }
+
+    pub(crate) async fn find_image_bytes(&self, url: &Url) -> Result<Option<Vec<u8>>, Error> {
+        let model = Image::find_by_id(url.to_string()).one(&self.db).await?;
+
+        match model {
+            Some(model) => Ok(Some(zstd_decompress(&model.image).await?)),
+            None => Ok(None),
         }
     }
 
     pub(crate) async fn insert_image<T>(&self, url: &Url, bytes: T) -> Result<(), Error>
+    where
+        T: AsRef<[u8]>,
+    {
+        let _guard = self.writes.read().await;
+        self.insert_image_unguarded(url, bytes).await
+    }
+
+    /// Insert the image in a background task, `flush` waits for the task to finish
+    pub(crate) async fn insert_image_behind(&self, url: &Url, bytes: Vec<u8>) {
+        // The lock is taken before spawning, so that a `flush` called after this returns waits for the write
+        let guard = Arc::clone(&self.writes).read_owned().await;
+        let db = self.clone();
+        let url = url.clone();
+
+        tokio::spawn(async move {
+            if let Err(error) = db.insert_image_unguarded(&url, bytes).await {
+                error!("Failed to cache the image: `{url}`, {error}");
+            }
+            drop(guard);
+        });
+    }
+
+    async fn insert_image_unguarded<T>(&self, url: &Url, bytes: T) -> Result<(), Error>
     where
         T: AsRef<[u8]>,
     {
@@ -144,10 +477,159 @@ impl NovelDB {
         };
         model.insert(&self.db).await?;
 
+        self.mark_written();
+
         Ok(())
     }
 
-    fn db_path(app_name: &str) -> Result<PathBuf, Error> {
+    pub(crate) async fn update_image<T>(&self, url: &Url, bytes: T) -> Result<(), Error>
+    where
+        T: AsRef<[u8]>,
+    {
+        let _guard = self.writes.read().await;
+
+        let model = entity::image::ActiveModel {
+            url: sea_orm::Set(url.to_string()),
+            image: sea_orm::Set(zstd_compress(bytes).await?),
+        };
+        model.update(&self.db).await?;
+
+        self.mark_written();
+
+        Ok(())
+    }
+
+    /// Insert or update the image, depending on whether it is already cached
+    pub(crate) async fn save_image<T>(&self, url: &Url, bytes: T) -> Result<(), Error>
+    where
+        T: AsRef<[u8]>,
+    {
+        let bytes = bytes.as_ref();
+        image::guess_format(bytes)?;
+
+        match self.find_image_bytes(url).await? {
+            Some(_) => self.update_image(url, bytes).await,
+            None => self.insert_image(url, bytes).await,
+        }
+    }
+
+    /// Note the time of a write, which tells `import_cache` whether the local cache is newer than an archive
+    fn mark_written(&self) {
+        self.last_write
+            .store(chrono::Utc::now().timestamp_millis(), Ordering::Relaxed);
+    }
+
+    /// Record the time of the last write in the database, if there has been a write since the last record
+    async fn record_last_write(&self) -> Result<(), Error> {
+        let last_write = self.last_write.swap(0, Ordering::Relaxed);
+        if last_write == 0 {
+            return Ok(());
+        }
+
+        let model = entity::metadata::ActiveModel {
+            key: sea_orm::Set(NovelDB::LAST_WRITE.to_string()),
+            value: sea_orm::Set(last_write.to_string()),
+        };
+        Metadata::insert(model)
+            .on_conflict(
+                OnConflict::column(entity::metadata::Column::Key)
+                    .update_column(entity::metadata::Column::Value)
+                    .to_owned(),
+            )
+            .exec(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// The Unix timestamp in milliseconds of the last write, `None` if nothing has been written since it is recorded
+    pub(crate) async fn last_write(&self) -> Result<Option<i64>, Error> {
+        let last_write = self.last_write.load(Ordering::Relaxed);
+        if last_write != 0 {
+            return Ok(Some(last_write));
+        }
+
+        let model = Metadata::find_by_id(NovelDB::LAST_WRITE.to_string())
+            .one(&self.db)
+            .await?;
+
+        Ok(model.and_then(|model| model.value.parse().ok()))
+    }
+
+    /// Wait for all in-flight writes to finish, record the time of the last write,
+    /// then checkpoint the WAL into the database file
+    pub(crate) async fn flush(&self) -> Result<(), Error> {
+        let _guard = self.writes.write().await;
+
+        self.record_last_write().await?;
+
+        self.db
+            .execute(Statement::from_string(
+                DatabaseBackend::Sqlite,
+                "PRAGMA wal_checkpoint(TRUNCATE)".to_string(),
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    /// The size of the database in bytes, counted in pages, so that it works whether or not it is backed by a file
+    pub(crate) async fn total_size(&self) -> Result<u64, Error> {
+        self.query_size(
+            "SELECT page_count * page_size AS size FROM pragma_page_count(), pragma_page_size()",
+        )
+        .await
+    }
+
+    /// The bytes stored in the texts, including the previews and the earlier versions, and in the images
+    pub(crate) async fn stored_size(&self) -> Result<(u64, u64), Error> {
+        let texts = self
+            .query_size(
+                "SELECT (SELECT COALESCE(SUM(LENGTH(text)), 0) FROM text) \
+                 + (SELECT COALESCE(SUM(LENGTH(text)), 0) FROM preview) \
+                 + (SELECT COALESCE(SUM(LENGTH(text)), 0) FROM version) AS size",
+            )
+            .await?;
+        let images = self
+            .query_size("SELECT COALESCE(SUM(LENGTH(image)), 0) AS size FROM image")
+            .await?;
+
+        Ok((texts, images))
+    }
+
+    async fn query_size(&self, sql: &str) -> Result<u64, Error> {
+        let size = match self
+            .db
+            .query_one(Statement::from_string(
+                DatabaseBackend::Sqlite,
+                sql.to_string(),
+            ))
+            .await?
+        {
+            Some(row) => row.try_get::<i64>("", "size")?,
+            None => 0,
+        };
+
+        Ok(size as u64)
+    }
+
+    /// Write a consistent snapshot of the database to `path`, which must not exist
+    pub(crate) async fn backup<T>(&self, path: T) -> Result<(), Error>
+    where
+        T: AsRef<Path>,
+    {
+        self.db
+            .execute(Statement::from_sql_and_values(
+                DatabaseBackend::Sqlite,
+                "VACUUM INTO ?",
+                [path.as_ref().display().to_string().into()],
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    pub(crate) fn db_path(app_name: &str) -> Result<PathBuf, Error> {
         let mut db_path = crate::data_dir_path(app_name)?;
         db_path.push(NovelDB::DB_NAME);
 
@@ -155,6 +637,54 @@ impl NovelDB {
     }
 }
 
+/// The schema version of the cache database written by this version of the crate
+#[must_use]
+pub fn latest_schema_version() -> String {
+    Migrator::migrations()
+        .last()
+        .map(|migration| migration.name().to_string())
+        .unwrap()
+}
+
+/// Get the schema version of the cache database at `path`, `None` if it has never been migrated
+///
+/// A version different from [`latest_schema_version`] means the database was written by a newer or older crate
+pub async fn schema_version<T>(path: T) -> Result<Option<String>, Error>
+where
+    T: AsRef<Path>,
+{
+    NovelDB::open(path).await?.schema_version().await
+}
+
+/// The size of the cache of a client, the images are counted in the database or in `image_cache_dir` if it is set
+pub(crate) async fn cache_size(
+    db: &NovelDB,
+    image_cache_dir: Option<&Path>,
+) -> Result<CacheSize, Error> {
+    let (texts, mut images) = db.stored_size().await?;
+    let mut total = db.total_size().await?;
+
+    if let Some(dir) = image_cache_dir {
+        let size = crate::image_cache_size(dir).await?;
+        images += size;
+        total += size;
+    }
+
+    Ok(CacheSize {
+        texts,
+        images,
+        total,
+    })
+}
+
+/// Apply all pending migrations to the cache database at `path`
+pub async fn migrate<T>(path: T) -> Result<(), Error>
+where
+    T: AsRef<Path>,
+{
+    NovelDB::open(path).await?.migrate().await
+}
+
 async fn zstd_decompress<T>(data: T) -> Result<Vec<u8>, Error>
 where
     T: AsRef<[u8]>,
@@ -186,7 +716,7 @@ mod tests {
 
     use std::str::FromStr;
 
-    use chrono::NaiveDateTime;
+    use futures_util::future;
     use pretty_assertions::assert_eq;
 
     use crate::Identifier;
@@ -256,4 +786,317 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn db_update_identical_text() -> Result<(), Error> {
+        let db = NovelDB::new("test-app-identical").await?;
+        let contents = "test-contents";
+
+        let chapter_info_old = ChapterInfo {
+            identifier: Identifier::Id(0),
+            update_time: Some(NaiveDateTime::from_str("2020-07-08T15:25:15")?),
+            ..Default::default()
+        };
+
+        let chapter_info_new = ChapterInfo {
+            identifier: Identifier::Id(0),
+            update_time: Some(NaiveDateTime::from_str("2020-07-08T15:25:17")?),
+            ..Default::default()
+        };
+
+        // Appending an empty frame gives a blob that differs from a fresh compression of the same text
+        let mut blob = zstd_compress(contents).await?;
+        blob.extend(zstd_compress("").await?);
+
+        let model = entity::text::ActiveModel {
+            identifier: sea_orm::Set(chapter_info_old.identifier.to_string()),
+            date_time: sea_orm::Set(chapter_info_old.update_time),
+            text: sea_orm::Set(blob.clone()),
+        };
+        model.insert(&db.db).await?;
+
+        db.update_text(&chapter_info_new, contents).await?;
+
+        let model = Text::find_by_id(chapter_info_new.identifier.to_string())
+            .one(&db.db)
+            .await?
+            .unwrap();
+        assert_eq!(model.text, blob);
+        assert_eq!(model.date_time, chapter_info_new.update_time);
+
+        db.update_text(&chapter_info_new, "new-contents").await?;
+        assert_eq!(
+            db.find_text(&chapter_info_new).await?,
+            FindTextResult::Ok(String::from("new-contents"))
+        );
+
+        db.drop().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn db_find_stale_text() -> Result<(), Error> {
+        let db = NovelDB::new("test-app-stale").await?;
+
+        let chapter_info_old = ChapterInfo {
+            identifier: Identifier::Id(0),
+            update_time: Some(NaiveDateTime::from_str("2020-07-08T15:25:15")?),
+            ..Default::default()
+        };
+
+        let chapter_info_new = ChapterInfo {
+            identifier: Identifier::Id(0),
+            update_time: Some(NaiveDateTime::from_str("2020-07-08T15:25:17")?),
+            ..Default::default()
+        };
+
+        assert_eq!(db.find_stale_text(&chapter_info_new).await?, None);
+
+        db.insert_text(&chapter_info_old, "old-contents").await?;
+        assert_eq!(
+            db.find_text(&chapter_info_new).await?,
+            FindTextResult::Outdate
+        );
+        assert_eq!(
+            db.find_stale_text(&chapter_info_new).await?,
+            Some(String::from("old-contents"))
+        );
+
+        db.drop().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn db_preview() -> Result<(), Error> {
+        let db = NovelDB::new("test-app-preview").await?;
+
+        let chapter_info_old = ChapterInfo {
+            identifier: Identifier::Id(0),
+            update_time: Some(NaiveDateTime::from_str("2020-07-08T15:25:15")?),
+            ..Default::default()
+        };
+
+        let chapter_info_new = ChapterInfo {
+            identifier: Identifier::Id(0),
+            update_time: Some(NaiveDateTime::from_str("2020-07-08T15:25:17")?),
+            ..Default::default()
+        };
+
+        assert_eq!(db.find_preview(&chapter_info_old).await?, None);
+
+        db.save_preview(&chapter_info_old, "old-preview").await?;
+        assert_eq!(
+            db.find_preview(&chapter_info_old).await?,
+            Some(String::from("old-preview"))
+        );
+        assert_eq!(db.find_preview(&chapter_info_new).await?, None);
+        assert_eq!(db.find_text(&chapter_info_old).await?, FindTextResult::None);
+
+        db.save_preview(&chapter_info_new, "new-preview").await?;
+        assert_eq!(
+            db.find_preview(&chapter_info_new).await?,
+            Some(String::from("new-preview"))
+        );
+
+        db.drop().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn db_last_write() -> Result<(), Error> {
+        let db = NovelDB::temp().await?;
+        let chapter_info = ChapterInfo {
+            identifier: Identifier::Id(0),
+            ..Default::default()
+        };
+        assert_eq!(db.last_write().await?, None);
+
+        // The time of the write is only recorded in the database by `flush`
+        db.insert_text(&chapter_info, "contents").await?;
+        let last_write = db.last_write().await?;
+        assert!(last_write.is_some());
+        assert!(Metadata::find().all(&db.db).await?.is_empty());
+
+        db.flush().await?;
+        assert_eq!(db.last_write().await?, last_write);
+        assert_eq!(Metadata::find().all(&db.db).await?.len(), 1);
+
+        // Rewriting the same text is not a write
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        db.update_text(&chapter_info, "contents").await?;
+        assert_eq!(db.last_write().await?, last_write);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn db_size() -> Result<(), Error> {
+        let db = NovelDB::new("test-app-size").await?;
+        let chapter_info = ChapterInfo {
+            identifier: Identifier::Id(0),
+            ..Default::default()
+        };
+
+        assert_eq!(db.stored_size().await?, (0, 0));
+
+        db.insert_text(&chapter_info, "test-contents".repeat(100))
+            .await?;
+        let (texts, images) = db.stored_size().await?;
+        assert!(texts > 0);
+        assert_eq!(images, 0);
+
+        let size = cache_size(&db, None).await?;
+        assert_eq!(size.texts, texts);
+        assert!(size.total >= texts);
+
+        db.drop().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn db_schema_version() -> Result<(), Error> {
+        let app_name = "test-app-schema";
+
+        let db = NovelDB::new(app_name).await?;
+        let db_path = NovelDB::db_path(app_name)?;
+
+        assert_eq!(
+            schema_version(&db_path).await?,
+            Some(latest_schema_version())
+        );
+
+        db.drop().await?;
+        assert_eq!(schema_version(&db_path).await?, None);
+
+        let empty_path =
+            std::env::temp_dir().join(format!("novel-api-test-{}.db", uuid::Uuid::new_v4()));
+        let empty = NovelDB::connect(&empty_path, true).await?;
+        assert_eq!(schema_version(&empty_path).await?, None);
+        // Inspecting the schema version does not create the migration table
+        assert!(empty
+            .query_one(Statement::from_string(
+                DatabaseBackend::Sqlite,
+                "SELECT name FROM sqlite_master WHERE name = 'seaql_migrations'".to_string(),
+            ))
+            .await?
+            .is_none());
+
+        migrate(&db_path).await?;
+        assert_eq!(db.schema_version().await?, Some(latest_schema_version()));
+
+        db.drop().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn db_save_text() -> Result<(), Error> {
+        let db = NovelDB::new("test-app-save").await?;
+
+        let chapter_info = ChapterInfo {
+            identifier: Identifier::Id(0),
+            ..Default::default()
+        };
+
+        assert!(db.save_text(&chapter_info, " \n ").await.is_err());
+
+        db.save_text(&chapter_info, "old-contents").await?;
+        db.save_text(&chapter_info, "new-contents").await?;
+        assert_eq!(
+            db.find_text(&chapter_info).await?,
+            FindTextResult::Ok(String::from("new-contents"))
+        );
+
+        db.drop().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn db_insert_image_behind() -> Result<(), Error> {
+        let db = NovelDB::new("test-app-image-behind").await?;
+
+        let urls = (0..50)
+            .map(|id| Url::parse(&format!("https://example.com/{id}.png")))
+            .collect::<Result<Vec<_>, _>>()?;
+        for url in &urls {
+            db.insert_image_behind(url, vec![0; 1024]).await;
+        }
+
+        db.flush().await?;
+        for url in &urls {
+            assert_eq!(db.find_image_bytes(url).await?, Some(vec![0; 1024]));
+        }
+
+        db.drop().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn db_delete_texts() -> Result<(), Error> {
+        let db = NovelDB::new("test-app-delete").await?;
+
+        for id in 0..3 {
+            let chapter_info = ChapterInfo {
+                identifier: Identifier::Id(id),
+                ..Default::default()
+            };
+            db.insert_text(&chapter_info, "contents").await?;
+        }
+
+        let deleted = db
+            .delete_texts(&[Identifier::Id(0), Identifier::Id(1), Identifier::Id(5)])
+            .await?;
+        assert_eq!(deleted, 2);
+
+        let chapter_info = ChapterInfo {
+            identifier: Identifier::Id(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            db.find_text(&chapter_info).await?,
+            FindTextResult::Ok(String::from("contents"))
+        );
+
+        db.drop().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn db_concurrent_insert() -> Result<(), Error> {
+        let db = NovelDB::new("test-app-concurrent").await?;
+
+        let chapter_infos = (0..100)
+            .map(|id| ChapterInfo {
+                identifier: Identifier::Id(id),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+
+        future::try_join_all(
+            chapter_infos
+                .iter()
+                .map(|chapter_info| db.insert_text(chapter_info, "test-contents")),
+        )
+        .await?;
+
+        db.flush().await?;
+
+        for chapter_info in &chapter_infos {
+            assert_eq!(
+                db.find_text(chapter_info).await?,
+                FindTextResult::Ok(String::from("test-contents"))
+            );
+        }
+
+        db.drop().await?;
+
+        Ok(())
+    }
 }
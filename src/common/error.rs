@@ -1,4 +1,10 @@
-use http::StatusCode;
+use std::{
+    io::ErrorKind,
+    time::{Duration, SystemTime},
+};
+
+use chrono::DateTime;
+use http::{header::RETRY_AFTER, HeaderMap, StatusCode};
 use thiserror::Error;
 
 /// novel-api error
@@ -34,6 +40,8 @@ pub enum Error {
     #[error(transparent)]
     Image(#[from] image::ImageError),
     #[error(transparent)]
+    TokioJoin(#[from] tokio::task::JoinError),
+    #[error(transparent)]
     Keyring(#[from] keyring::Error),
     #[error(transparent)]
     Url(#[from] url::ParseError),
@@ -45,6 +53,133 @@ pub enum Error {
     StatusCode(#[from] http::status::InvalidStatusCode),
     #[error("{0}")]
     NovelApi(String),
+    #[error("Wrong username or password: `{0}`")]
+    InvalidCredentials(String),
+    #[error("Not logged in")]
+    NotLoggedIn,
+    #[error("Already signed in today")]
+    AlreadySignedIn,
+    #[error("The chapter is locked: `{0}`")]
+    ChapterLocked(String),
+    #[error("The site is not available in this region, try a proxy: `{0}`")]
+    RegionBlocked(String),
+    #[error("The novel is unavailable: `{reason}`")]
+    NovelUnavailable { reason: String },
     #[error("The HTTP request failed, status code: `{code}`, message: `{msg}`")]
-    Http { code: StatusCode, msg: String },
+    Http {
+        code: StatusCode,
+        msg: String,
+        /// Selected headers of the response, e.g. `Retry-After` and rate limit headers,
+        /// empty if the failure is reported in the body of a successful response
+        headers: HeaderMap,
+    },
+}
+
+impl Error {
+    /// Whether the failed operation may succeed if retried,
+    /// e.g. timeouts, connection errors, 5xx and 429 responses
+    #[must_use]
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Error::Reqwest(error) => {
+                error.is_timeout()
+                    || error.is_connect()
+                    || crate::is_some_and(error.status(), Error::is_retryable_status)
+            }
+            Error::Http { code, .. } => Error::is_retryable_status(*code),
+            Error::StdIo(error) => matches!(
+                error.kind(),
+                ErrorKind::TimedOut
+                    | ErrorKind::Interrupted
+                    | ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+            ),
+            _ => false,
+        }
+    }
+
+    /// The selected response headers of a failed HTTP request, see [`Error::Http`]
+    #[must_use]
+    pub fn headers(&self) -> Option<&HeaderMap> {
+        match self {
+            Error::Http { headers, .. } => Some(headers),
+            _ => None,
+        }
+    }
+
+    /// How long the server asks to wait before retrying, from the `Retry-After` header
+    #[must_use]
+    pub fn retry_after(&self) -> Option<Duration> {
+        let value = self.headers()?.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+        match value.parse::<u64>() {
+            Ok(seconds) => Some(Duration::from_secs(seconds)),
+            Err(_) => {
+                let date = DateTime::parse_from_rfc2822(value).ok()?;
+                let date =
+                    SystemTime::UNIX_EPOCH + Duration::from_secs(date.timestamp().try_into().ok()?);
+                Some(date.duration_since(SystemTime::now()).unwrap_or_default())
+            }
+        }
+    }
+
+    #[must_use]
+    fn is_retryable_status(code: StatusCode) -> bool {
+        code.is_server_error() || code == StatusCode::TOO_MANY_REQUESTS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_retryable() -> Result<(), Error> {
+        let http_error = |code| Error::Http {
+            code,
+            msg: String::default(),
+            headers: HeaderMap::new(),
+        };
+
+        assert!(http_error(StatusCode::INTERNAL_SERVER_ERROR).is_retryable());
+        assert!(http_error(StatusCode::BAD_GATEWAY).is_retryable());
+        assert!(http_error(StatusCode::TOO_MANY_REQUESTS).is_retryable());
+        assert!(!http_error(StatusCode::NOT_FOUND).is_retryable());
+        assert!(!http_error(StatusCode::UNAUTHORIZED).is_retryable());
+
+        assert!(Error::StdIo(std::io::Error::from(ErrorKind::TimedOut)).is_retryable());
+        assert!(!Error::StdIo(std::io::Error::from(ErrorKind::NotFound)).is_retryable());
+
+        assert!(!Error::InvalidCredentials(String::default()).is_retryable());
+        assert!(!Error::NovelApi(String::default()).is_retryable());
+
+        Ok(())
+    }
+
+    #[test]
+    fn retry_after() -> Result<(), Error> {
+        let http_error = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(RETRY_AFTER, value.parse().unwrap());
+
+            Error::Http {
+                code: StatusCode::TOO_MANY_REQUESTS,
+                msg: String::default(),
+                headers,
+            }
+        };
+
+        assert_eq!(
+            http_error("120").retry_after(),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            http_error("Sun, 09 Sep 2001 01:46:40 GMT").retry_after(),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(http_error("soon").retry_after(), None);
+        assert_eq!(Error::NotLoggedIn.retry_after(), None);
+
+        Ok(())
+    }
 }
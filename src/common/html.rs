@@ -0,0 +1,136 @@
+use async_trait::async_trait;
+
+use crate::{Client, ContentInfo, ContentInfos, Error};
+
+/// Extension methods of content information
+#[async_trait]
+pub trait ContentInfosExt {
+    /// Render the content as HTML, images are downloaded through the client
+    /// and inlined as base64 data URIs, so the result does not depend on any external resource
+    async fn to_self_contained_html<T>(&self, client: &T) -> Result<String, Error>
+    where
+        T: Client + Sync;
+
+    /// Whether the content has images and no text, e.g. a manga page
+    #[must_use]
+    fn is_image_only(&self) -> bool;
+
+    /// The share of text among the content, from 0 for no text to 1 for no images, 0 if there is no content
+    #[must_use]
+    fn text_ratio(&self) -> f64;
+}
+
+#[async_trait]
+impl ContentInfosExt for ContentInfos {
+    async fn to_self_contained_html<T>(&self, client: &T) -> Result<String, Error>
+    where
+        T: Client + Sync,
+    {
+        let mut result = Vec::with_capacity(self.len());
+
+        for content_info in self {
+            match content_info {
+                ContentInfo::Text(text) => result.push(format!("<p>{}</p>", escape_html(text))),
+                ContentInfo::Image(url) => {
+                    let bytes = client.image_bytes(url).await?;
+                    let mime_type = image::guess_format(&bytes)?.to_mime_type();
+
+                    result.push(format!(
+                        r#"<img src="data:{mime_type};base64,{}" />"#,
+                        base64_simd::STANDARD.encode_to_string(&bytes)
+                    ));
+                }
+            }
+        }
+
+        Ok(result.join("\n"))
+    }
+
+    fn is_image_only(&self) -> bool {
+        !self.is_empty()
+            && self
+                .iter()
+                .all(|content_info| matches!(content_info, ContentInfo::Image(_)))
+    }
+
+    fn text_ratio(&self) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+
+        let texts = self
+            .iter()
+            .filter(|content_info| matches!(content_info, ContentInfo::Text(_)))
+            .count();
+
+        texts as f64 / self.len() as f64
+    }
+}
+
+#[must_use]
+fn escape_html(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            _ => result.push(c),
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::SfacgClient;
+
+    #[tokio::test]
+    async fn to_self_contained_html() -> Result<(), Error> {
+        let client = SfacgClient::new().await?;
+
+        let content_infos = vec![
+            ContentInfo::Text(String::from("第一章")),
+            ContentInfo::Text(String::from("<b>\"Tom\" & 'Jerry'</b>")),
+        ];
+
+        assert_eq!(
+            content_infos.to_self_contained_html(&client).await?,
+            "<p>第一章</p>\n<p>&lt;b&gt;&quot;Tom&quot; &amp; &#39;Jerry&#39;&lt;/b&gt;</p>"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn text_ratio() -> Result<(), Error> {
+        let image = || ContentInfo::Image(url::Url::parse("https://example.com/1.jpg").unwrap());
+
+        let content_infos = vec![image(), image()];
+        assert!(content_infos.is_image_only());
+        assert_eq!(content_infos.text_ratio(), 0.0);
+
+        let content_infos = vec![
+            ContentInfo::Text(String::from("第一行")),
+            image(),
+            ContentInfo::Text(String::from("第二行")),
+            image(),
+        ];
+        assert!(!content_infos.is_image_only());
+        assert_eq!(content_infos.text_ratio(), 0.5);
+
+        let content_infos = ContentInfos::new();
+        assert!(!content_infos.is_image_only());
+        assert_eq!(content_infos.text_ratio(), 0.0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+use std::{collections::HashMap, time::Duration};
+
+use parking_lot::Mutex;
+use reqwest::Request;
+
+/// Latency statistics of an endpoint
+#[must_use]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EndpointMetrics {
+    /// Number of requests
+    pub count: u64,
+    /// Total time spent on the requests
+    pub total: Duration,
+    /// Time spent on the slowest request
+    pub max: Duration,
+}
+
+impl EndpointMetrics {
+    /// Average time spent on a request
+    #[must_use]
+    pub fn average(&self) -> Duration {
+        if self.count == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_nanos((self.total.as_nanos() / self.count as u128) as u64)
+        }
+    }
+}
+
+/// Collects the latency of the requests sent by the clients, grouped by endpoint
+#[must_use]
+#[derive(Default)]
+pub(crate) struct MetricsCollector {
+    endpoints: Mutex<HashMap<String, EndpointMetrics>>,
+}
+
+impl MetricsCollector {
+    pub(crate) fn record(&self, endpoint: String, elapsed: Duration) {
+        let mut endpoints = self.endpoints.lock();
+        let metrics = endpoints.entry(endpoint).or_default();
+
+        metrics.count += 1;
+        metrics.total += elapsed;
+        metrics.max = metrics.max.max(elapsed);
+    }
+
+    pub(crate) fn snapshot(&self) -> HashMap<String, EndpointMetrics> {
+        self.endpoints.lock().clone()
+    }
+
+    /// The method, host and path of the request, with the path segments that contain digits replaced by `{id}`
+    #[must_use]
+    pub(crate) fn endpoint(request: &Request) -> String {
+        let url = request.url();
+
+        let path = url
+            .path()
+            .split('/')
+            .map(|segment| {
+                if segment.bytes().any(|byte| byte.is_ascii_digit()) {
+                    "{id}"
+                } else {
+                    segment
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+
+        format!(
+            "{} {}{path}",
+            request.method(),
+            url.host_str().unwrap_or_default()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+    use reqwest::Method;
+    use url::Url;
+
+    use crate::Error;
+
+    #[test]
+    fn metrics_collector() -> Result<(), Error> {
+        let collector = MetricsCollector::default();
+
+        for (id, millis) in [(1, 10), (2, 30)] {
+            let url = Url::parse(&format!("https://api.sfacg.com/novels/{id}/dirs"))?;
+            let endpoint = MetricsCollector::endpoint(&Request::new(Method::GET, url));
+            collector.record(endpoint, Duration::from_millis(millis));
+        }
+
+        let snapshot = collector.snapshot();
+        assert_eq!(snapshot.len(), 1);
+
+        let metrics = snapshot["GET api.sfacg.com/novels/{id}/dirs"];
+        assert_eq!(metrics.count, 2);
+        assert_eq!(metrics.max, Duration::from_millis(30));
+        assert_eq!(metrics.average(), Duration::from_millis(20));
+
+        Ok(())
+    }
+
+    #[test]
+    fn average_of_many_requests() -> Result<(), Error> {
+        let count = u32::MAX as u64 + 2;
+        let metrics = EndpointMetrics {
+            count,
+            total: Duration::from_millis(count),
+            max: Duration::from_millis(1),
+        };
+        assert_eq!(metrics.average(), Duration::from_millis(1));
+
+        Ok(())
+    }
+}
@@ -1,12 +1,21 @@
+mod archive;
 mod client;
 mod database;
 mod error;
+mod html;
+mod metrics;
 mod net;
+mod reader;
 mod utils;
 
 pub use client::*;
+pub use database::{latest_schema_version, migrate, schema_version};
 pub use error::*;
+pub use html::*;
+pub use metrics::*;
+pub use reader::*;
 pub use utils::*;
 
+pub(crate) use archive::*;
 pub(crate) use database::*;
 pub(crate) use net::*;
@@ -1,40 +1,164 @@
 use std::{
     io::BufWriter,
-    ops::Deref,
     path::{Path, PathBuf},
     sync::Arc,
     time::Duration,
 };
 
+use async_trait::async_trait;
 use http::StatusCode;
 use parking_lot::RwLock;
 use reqwest::{
-    header::{HeaderMap, HeaderValue, ACCEPT, ACCEPT_LANGUAGE, CONNECTION},
-    Certificate, Client, Proxy,
+    cookie::CookieStore as _,
+    header::CONTENT_TYPE,
+    header::{
+        HeaderMap, HeaderValue, ACCEPT, ACCEPT_LANGUAGE, CONNECTION, COOKIE, SET_COOKIE, USER_AGENT,
+    },
+    tls, Certificate, Client, IntoUrl, Proxy, Request, RequestBuilder, Response,
 };
 use reqwest_cookie_store::{CookieStore, CookieStoreMutex};
+use serde::de::DeserializeOwned;
 use tokio::fs;
 use tracing::{error, info};
 use url::Url;
 
-use crate::Error;
+use crate::{Error, MetricsCollector, Timing, TlsVersion};
 
-#[inline]
-pub(crate) fn check_status<T>(code: StatusCode, msg: T) -> Result<(), Error>
+/// Signatures of the 403 pages returned instead of the Api response when the site blocks the region of the client,
+/// both sites are behind Cloudflare, whose country block page is error 1009
+const REGION_BLOCKED_SIGNATURES: [&str; 2] = [
+    "error code 1009",
+    "has banned the country or region your ip address is in",
+];
+
+/// Prefixes of the names of the rate limit headers kept in [`Error::Http`]
+const ERROR_HEADER_PREFIXES: [&str; 2] = ["x-ratelimit-", "ratelimit-"];
+
+/// Names of the other headers kept in [`Error::Http`], which tell when to retry or who blocked the request
+const ERROR_HEADERS: [&str; 4] = ["retry-after", "server", "cf-ray", "cf-mitigated"];
+
+/// Return an error if the status code is not 200, [`Error::RegionBlocked`] if the body is a region block page
+pub(crate) async fn check_status<T>(response: Response, msg: T) -> Result<Response, Error>
 where
     T: AsRef<str>,
 {
-    if code != StatusCode::OK {
-        return Err(Error::Http {
-            code,
-            msg: msg.as_ref().trim().to_string(),
-        });
+    let code = response.status();
+    if code == StatusCode::OK {
+        return Ok(response);
+    }
+
+    let msg = msg.as_ref().trim().to_string();
+    let headers = error_headers(response.headers());
+    let body = response.text().await.unwrap_or_default();
+    if is_region_blocked(code, &body) {
+        return Err(Error::RegionBlocked(msg));
+    }
+
+    Err(Error::Http { code, msg, headers })
+}
+
+/// The headers of a failed response that help to diagnose it, e.g. `Retry-After` and rate limit headers
+#[must_use]
+pub(crate) fn error_headers(headers: &HeaderMap) -> HeaderMap {
+    headers
+        .iter()
+        .filter(|(name, _)| {
+            let name = name.as_str();
+            ERROR_HEADERS.contains(&name)
+                || ERROR_HEADER_PREFIXES
+                    .iter()
+                    .any(|prefix| name.starts_with(prefix))
+        })
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect()
+}
+
+/// Whether the response is a region block rather than a response of the Api,
+/// i.e. the status code is 451, or it is 403 and the body is the Cloudflare country block page,
+/// other failed responses, e.g. 5xx error pages, are not region blocks whatever their body says
+#[must_use]
+pub(crate) fn is_region_blocked(code: StatusCode, body: &str) -> bool {
+    match code {
+        StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS => true,
+        StatusCode::FORBIDDEN => {
+            let body = body.to_lowercase();
+            REGION_BLOCKED_SIGNATURES
+                .iter()
+                .any(|signature| body.contains(signature))
+        }
+        _ => false,
     }
+}
 
-    Ok(())
+/// Return an error instead of panicking when a successful response has no data
+#[inline]
+pub(crate) fn check_data<T>(data: Option<T>) -> Result<T, Error> {
+    data.ok_or_else(|| Error::NovelApi("The response data is empty".to_string()))
 }
 
 #[must_use]
+pub(crate) fn content_type(response: &Response) -> Option<String> {
+    response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+}
+
+#[async_trait]
+pub(crate) trait ResponseExt {
+    /// Read the body as text, decoding it with the charset of the `Content-Type` header
+    async fn text_decoded(self) -> Result<String, Error>;
+
+    /// Deserialize the body as JSON, decoding it with the charset of the `Content-Type` header
+    async fn json_decoded<T>(self) -> Result<T, Error>
+    where
+        T: DeserializeOwned;
+}
+
+#[async_trait]
+impl ResponseExt for Response {
+    async fn text_decoded(self) -> Result<String, Error> {
+        let content_type = content_type(&self);
+        crate::decode_text(self.bytes().await?, content_type.as_deref())
+    }
+
+    async fn json_decoded<T>(self) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        let code = self.status();
+        let url = self.url().clone();
+        let headers = error_headers(self.headers());
+        let text = self.text_decoded().await?;
+
+        if is_region_blocked(code, &text) {
+            return Err(Error::RegionBlocked(format!(
+                "HTTP request failed: `{url}`"
+            )));
+        }
+
+        match serde_json::from_str(&text) {
+            Ok(value) => Ok(value),
+            // A failed response that is not JSON, e.g. a rate limit page, is reported with its status code
+            Err(_) if !code.is_success() => Err(Error::Http {
+                code,
+                msg: format!("HTTP request failed: `{url}`"),
+                headers,
+            }),
+            Err(error) => Err(error)?,
+        }
+    }
+}
+
+/// Sends the requests of a [`HTTPClient`], tests replace it to supply canned responses without the network
+#[async_trait]
+pub(crate) trait Transport: Send + Sync {
+    async fn execute(&self, request: Request) -> Result<Response, Error>;
+}
+
+#[must_use]
+#[derive(Clone)]
 pub(crate) struct HTTPClientBuilder {
     app_name: &'static str,
     accept: HeaderValue,
@@ -45,6 +169,12 @@ pub(crate) struct HTTPClientBuilder {
     proxy: Option<Url>,
     no_proxy: bool,
     cert_path: Option<PathBuf>,
+    pinned_cert: Option<Vec<u8>>,
+    min_tls_version: Option<TlsVersion>,
+    pool_idle_timeout: Option<Duration>,
+    reqwest_client: Option<Client>,
+    transport: Option<Arc<dyn Transport>>,
+    metrics: Option<Arc<MetricsCollector>>,
 }
 
 impl HTTPClientBuilder {
@@ -63,6 +193,12 @@ impl HTTPClientBuilder {
             proxy: None,
             no_proxy: false,
             cert_path: None,
+            pinned_cert: None,
+            min_tls_version: None,
+            pool_idle_timeout: None,
+            reqwest_client: None,
+            transport: None,
+            metrics: None,
         }
     }
 
@@ -125,26 +261,85 @@ impl HTTPClientBuilder {
         }
     }
 
+    pub(crate) fn pinned_cert(self, pinned_cert: Option<Vec<u8>>) -> Self {
+        Self {
+            pinned_cert,
+            ..self
+        }
+    }
+
+    pub(crate) fn min_tls_version(self, min_tls_version: Option<TlsVersion>) -> Self {
+        Self {
+            min_tls_version,
+            ..self
+        }
+    }
+
+    pub(crate) fn pool_idle_timeout(self, pool_idle_timeout: Option<Duration>) -> Self {
+        Self {
+            pool_idle_timeout,
+            ..self
+        }
+    }
+
+    pub(crate) fn reqwest_client(self, reqwest_client: Option<Client>) -> Self {
+        Self {
+            reqwest_client,
+            ..self
+        }
+    }
+
+    pub(crate) fn transport(self, transport: Option<Arc<dyn Transport>>) -> Self {
+        Self { transport, ..self }
+    }
+
+    pub(crate) fn metrics(self, metrics: Option<Arc<MetricsCollector>>) -> Self {
+        Self { metrics, ..self }
+    }
+
     pub(crate) async fn build(self) -> Result<HTTPClient, Error> {
+        if self.cert_path.is_some() && self.pinned_cert.is_some() {
+            return Err(Error::NovelApi(
+                "The certificate for packet capture and the pinned certificate can not be used together"
+                    .to_string(),
+            ));
+        }
+
         let mut cookie_store = None;
         if self.cookie {
             cookie_store = Some(Arc::new(self.create_cookie_store().await?));
         }
 
+        let client = self.build_client(cookie_store.as_ref()).await?;
+
+        Ok(HTTPClient {
+            builder: self,
+            cookie_store: RwLock::new(cookie_store),
+            client: RwLock::new(client),
+        })
+    }
+
+    async fn build_client(
+        &self,
+        cookie_store: Option<&Arc<CookieStoreMutex>>,
+    ) -> Result<Client, Error> {
+        if let Some(reqwest_client) = &self.reqwest_client {
+            return Ok(reqwest_client.clone());
+        }
+
         let mut headers = HeaderMap::new();
-        headers.insert(ACCEPT, self.accept);
-        headers.insert(ACCEPT_LANGUAGE, self.accept_language);
+        headers.insert(ACCEPT, self.accept.clone());
+        headers.insert(ACCEPT_LANGUAGE, self.accept_language.clone());
         headers.insert(CONNECTION, HeaderValue::from_static("keep-alive"));
 
         let mut client_builder = Client::builder()
             .default_headers(headers)
             .http2_keep_alive_interval(Duration::from_secs(10))
             .http2_keep_alive_timeout(Duration::from_secs(60))
-            .user_agent(self.user_agent);
+            .user_agent(&self.user_agent);
 
-        if self.cookie {
-            client_builder =
-                client_builder.cookie_provider(Arc::clone(cookie_store.as_ref().unwrap()));
+        if let Some(cookie_store) = cookie_store {
+            client_builder = client_builder.cookie_provider(Arc::clone(cookie_store));
         }
 
         if !self.allow_compress {
@@ -153,24 +348,35 @@ impl HTTPClientBuilder {
             client_builder = client_builder.no_deflate();
         }
 
-        if let Some(proxy) = self.proxy {
-            client_builder = client_builder.proxy(Proxy::all(proxy)?);
+        if let Some(proxy) = &self.proxy {
+            client_builder = client_builder.proxy(Proxy::all(proxy.clone())?);
         }
 
         if self.no_proxy {
             client_builder = client_builder.no_proxy();
         }
 
-        if let Some(cert_path) = self.cert_path {
+        if let Some(cert_path) = &self.cert_path {
             let cert = Certificate::from_pem(&fs::read(cert_path).await?)?;
             client_builder = client_builder.add_root_certificate(cert);
         }
 
-        Ok(HTTPClient {
-            app_name: self.app_name,
-            cookie_store: RwLock::new(cookie_store),
-            client: client_builder.build()?,
-        })
+        if let Some(pinned_cert) = &self.pinned_cert {
+            let cert = Certificate::from_der(pinned_cert)?;
+            client_builder = client_builder
+                .tls_built_in_root_certs(false)
+                .add_root_certificate(cert);
+        }
+
+        if let Some(min_tls_version) = self.min_tls_version {
+            client_builder = client_builder.min_tls_version(min_tls_version.into());
+        }
+
+        if let Some(pool_idle_timeout) = self.pool_idle_timeout {
+            client_builder = client_builder.pool_idle_timeout(pool_idle_timeout);
+        }
+
+        Ok(client_builder.build()?)
     }
 
     async fn create_cookie_store(&self) -> Result<CookieStoreMutex, Error> {
@@ -202,11 +408,20 @@ impl HTTPClientBuilder {
     }
 }
 
+impl From<TlsVersion> for tls::Version {
+    fn from(version: TlsVersion) -> Self {
+        match version {
+            TlsVersion::Tls1_2 => tls::Version::TLS_1_2,
+            TlsVersion::Tls1_3 => tls::Version::TLS_1_3,
+        }
+    }
+}
+
 #[must_use]
 pub(crate) struct HTTPClient {
-    app_name: &'static str,
+    builder: HTTPClientBuilder,
     cookie_store: RwLock<Option<Arc<CookieStoreMutex>>>,
-    client: Client,
+    client: RwLock<Client>,
 }
 
 impl HTTPClient {
@@ -214,6 +429,104 @@ impl HTTPClient {
         HTTPClientBuilder::new(app_name)
     }
 
+    /// Send the request, through the transport if one is set
+    pub(crate) async fn send(&self, request: RequestBuilder) -> Result<Response, Error> {
+        let request = self.prepare(request.build()?);
+
+        let response = match &self.builder.metrics {
+            Some(metrics) => {
+                let endpoint = MetricsCollector::endpoint(&request);
+                let mut timing = Timing::new();
+
+                let response = self.execute(request).await;
+                metrics.record(endpoint, timing.elapsed_duration()?);
+
+                response
+            }
+            None => self.execute(request).await,
+        }?;
+
+        if self.builder.reqwest_client.is_some() {
+            if let Some(cookie_store) = self.cookie_store.read().as_ref() {
+                cookie_store.set_cookies(
+                    &mut response.headers().get_all(SET_COOKIE).iter(),
+                    response.url(),
+                );
+            }
+        }
+
+        Ok(response)
+    }
+
+    /// A supplied reqwest client has neither the default headers nor the cookie store of the crate,
+    /// so they are added to each request
+    #[must_use]
+    fn prepare(&self, mut request: Request) -> Request {
+        if self.builder.reqwest_client.is_none() {
+            return request;
+        }
+
+        let mut headers = vec![
+            (ACCEPT, self.builder.accept.clone()),
+            (ACCEPT_LANGUAGE, self.builder.accept_language.clone()),
+        ];
+        if let Ok(user_agent) = HeaderValue::from_str(&self.builder.user_agent) {
+            headers.push((USER_AGENT, user_agent));
+        }
+        if let Some(cookie_store) = self.cookie_store.read().as_ref() {
+            if let Some(cookies) = cookie_store.cookies(request.url()) {
+                headers.push((COOKIE, cookies));
+            }
+        }
+
+        for (name, value) in headers {
+            request.headers_mut().entry(name).or_insert(value);
+        }
+
+        request
+    }
+
+    async fn execute(&self, request: Request) -> Result<Response, Error> {
+        match &self.builder.transport {
+            Some(transport) => transport.execute(request).await,
+            None => {
+                let client = self.client.read().clone();
+                Ok(client.execute(request).await?)
+            }
+        }
+    }
+
+    pub(crate) fn get<T>(&self, url: T) -> RequestBuilder
+    where
+        T: IntoUrl,
+    {
+        self.client.read().get(url)
+    }
+
+    pub(crate) fn post<T>(&self, url: T) -> RequestBuilder
+    where
+        T: IntoUrl,
+    {
+        self.client.read().post(url)
+    }
+
+    pub(crate) fn put<T>(&self, url: T) -> RequestBuilder
+    where
+        T: IntoUrl,
+    {
+        self.client.read().put(url)
+    }
+
+    /// Rebuild the underlying client with the same settings, so that all pooled connections are dropped,
+    /// the cookies are kept, a supplied reqwest client is reused as is
+    pub(crate) async fn reset(&self) -> Result<(), Error> {
+        let cookie_store = self.cookie_store.read().clone();
+        let client = self.builder.build_client(cookie_store.as_ref()).await?;
+        *self.client.write() = client;
+
+        Ok(())
+    }
+
     pub(crate) fn add_cookie(&self, cookie_str: &str, url: &Url) -> Result<(), Error> {
         self.cookie_store
             .write()
@@ -226,9 +539,35 @@ impl HTTPClient {
         Ok(())
     }
 
+    pub(crate) fn export_cookies(&self) -> Result<String, Error> {
+        let mut json = Vec::new();
+        self.cookie_store
+            .read()
+            .as_ref()
+            .expect("Cookies not turned on")
+            .lock()
+            .unwrap()
+            .save_json(&mut json)?;
+
+        Ok(simdutf8::basic::from_utf8(&json)?.to_string())
+    }
+
+    pub(crate) fn import_cookies(&self, json: &str) -> Result<(), Error> {
+        let cookie_store = CookieStore::load_json(json.as_bytes())?;
+        *self
+            .cookie_store
+            .write()
+            .as_ref()
+            .expect("Cookies not turned on")
+            .lock()
+            .unwrap() = cookie_store;
+
+        Ok(())
+    }
+
     pub(crate) fn shutdown(&self) -> Result<(), Error> {
         if self.cookie_store.read().is_some() {
-            let cookie_path = HTTPClientBuilder::cookie_path(self.app_name)?;
+            let cookie_path = HTTPClientBuilder::cookie_path(self.builder.app_name)?;
 
             info!("Save the cookie file at: `{}`", cookie_path.display());
             let file = std::fs::File::create(cookie_path)?;
@@ -249,14 +588,6 @@ impl HTTPClient {
     }
 }
 
-impl Deref for HTTPClient {
-    type Target = Client;
-
-    fn deref(&self) -> &Self::Target {
-        &self.client
-    }
-}
-
 impl Drop for HTTPClient {
     fn drop(&mut self) {
         if let Err(error) = self.shutdown() {
@@ -264,3 +595,55 @@ impl Drop for HTTPClient {
         }
     }
 }
+
+/// A transport that answers requests with canned responses, matched by the request path and query,
+/// or by the path alone if there is no response for the query
+#[cfg(test)]
+#[must_use]
+#[derive(Default)]
+pub(crate) struct MockTransport {
+    responses: std::collections::HashMap<String, (StatusCode, HeaderMap, Vec<u8>)>,
+}
+
+#[cfg(test)]
+impl MockTransport {
+    pub(crate) fn response<T>(self, path: &str, code: StatusCode, body: T) -> Self
+    where
+        T: Into<Vec<u8>>,
+    {
+        self.response_with_headers(path, code, HeaderMap::new(), body)
+    }
+
+    pub(crate) fn response_with_headers<T>(
+        mut self,
+        path: &str,
+        code: StatusCode,
+        headers: HeaderMap,
+        body: T,
+    ) -> Self
+    where
+        T: Into<Vec<u8>>,
+    {
+        self.responses
+            .insert(path.to_string(), (code, headers, body.into()));
+        self
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl Transport for MockTransport {
+    async fn execute(&self, request: Request) -> Result<Response, Error> {
+        let url = request.url();
+        let (code, headers, body) = url
+            .query()
+            .and_then(|query| self.responses.get(&format!("{}?{query}", url.path())))
+            .or_else(|| self.responses.get(url.path()))
+            .cloned()
+            .unwrap_or((StatusCode::NOT_FOUND, HeaderMap::new(), Vec::new()));
+
+        let mut response = http::Response::builder().status(code).body(body).unwrap();
+        *response.headers_mut() = headers;
+        Ok(Response::from(response))
+    }
+}
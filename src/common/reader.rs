@@ -0,0 +1,108 @@
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use tokio::task::JoinHandle;
+use tracing::error;
+
+use crate::{ChapterInfo, Client, ContentInfos, Error};
+
+/// The background task downloading a prefetched chapter
+type Prefetching = JoinHandle<Result<ContentInfos, Error>>;
+
+/// Read the chapters of a novel by index, the content is cached by the client
+#[must_use]
+pub struct NovelReader<T>
+where
+    T: Client,
+{
+    client: Arc<T>,
+    chapter_infos: Arc<[ChapterInfo]>,
+    prefetch: bool,
+    /// The index of the prefetched chapter and the background task downloading it
+    prefetching: Mutex<Option<(usize, Prefetching)>>,
+}
+
+impl<T> NovelReader<T>
+where
+    T: Client + Send + Sync + 'static,
+{
+    /// Create a reader for the novel, the chapters of all volumes are flattened into one list
+    pub async fn new(client: Arc<T>, novel_id: u32) -> Result<NovelReader<T>, Error> {
+        let chapter_infos = client
+            .volume_infos(novel_id)
+            .await?
+            .into_iter()
+            .flat_map(|volume_info| volume_info.chapter_infos)
+            .collect();
+
+        Ok(Self {
+            client,
+            chapter_infos,
+            prefetch: false,
+            prefetching: Mutex::new(None),
+        })
+    }
+
+    /// Download the next chapter in the background after each read, the default is false
+    pub fn prefetch(&mut self, flag: bool) {
+        self.prefetch = flag;
+    }
+
+    /// The chapters of the novel
+    pub fn chapter_infos(&self) -> &[ChapterInfo] {
+        &self.chapter_infos
+    }
+
+    /// The number of chapters
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.chapter_infos.len()
+    }
+
+    /// Whether the novel has no chapters
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.chapter_infos.is_empty()
+    }
+
+    /// Get the content of the chapter at `index`
+    ///
+    /// If prefetch is enabled, the next downloadable chapter is downloaded in a background task,
+    /// which is used by the next read of that chapter and otherwise still fills the cache,
+    /// a failure to prefetch is logged and the chapter is downloaded again when it is read
+    pub async fn chapter(&self, index: usize) -> Result<ContentInfos, Error> {
+        let info = self.chapter_infos.get(index).ok_or_else(|| {
+            Error::NovelApi(format!(
+                "The chapter index {index} is out of range, the novel has {} chapters",
+                self.len()
+            ))
+        })?;
+
+        let prefetched = match self.prefetching.lock().take() {
+            Some((prefetched, task)) if prefetched == index => Some(task),
+            // A prefetch of another chapter is left running, it still caches the chapter
+            _ => None,
+        };
+
+        if self.prefetch
+            && crate::is_some_and(self.chapter_infos.get(index + 1), ChapterInfo::can_download)
+        {
+            let client = Arc::clone(&self.client);
+            let chapter_infos = Arc::clone(&self.chapter_infos);
+            let task =
+                tokio::spawn(async move { client.content_infos(&chapter_infos[index + 1]).await });
+
+            *self.prefetching.lock() = Some((index + 1, task));
+        }
+
+        if let Some(task) = prefetched {
+            match task.await {
+                Ok(Ok(content_infos)) => return Ok(content_infos),
+                Ok(Err(error)) => error!("Failed to prefetch chapter: `{}`, {error}", info.title),
+                Err(error) => error!("Failed to prefetch chapter: `{}`, {error}", info.title),
+            }
+        }
+
+        self.client.content_infos(info).await
+    }
+}
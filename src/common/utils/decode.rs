@@ -0,0 +1,155 @@
+use std::{io::Cursor, num::NonZeroUsize, thread};
+
+use image::{
+    error::{ImageFormatHint, LimitError, LimitErrorKind, UnsupportedError, UnsupportedErrorKind},
+    io::{Limits, Reader},
+    DynamicImage, ImageError,
+};
+use tokio::{sync::Semaphore, task};
+
+use crate::{Error, ImageLimits, ImageMeta};
+
+/// The default maximum number of images decoded at the same time
+#[must_use]
+pub(crate) fn default_image_decodes() -> usize {
+    thread::available_parallelism()
+        .map(NonZeroUsize::get)
+        .unwrap_or(4)
+}
+
+/// Decode the image on the blocking thread pool, waiting for a permit of `semaphore` first
+pub(crate) async fn decode_image_blocking(
+    bytes: Vec<u8>,
+    limits: ImageLimits,
+    semaphore: &Semaphore,
+) -> Result<DynamicImage, Error> {
+    let _permit = semaphore
+        .acquire()
+        .await
+        .expect("The semaphore is never closed");
+
+    task::spawn_blocking(move || decode_image(bytes, &limits)).await?
+}
+
+/// Decode the image, refusing images that exceed the limits
+pub(crate) fn decode_image<T>(bytes: T, limits: &ImageLimits) -> Result<DynamicImage, Error>
+where
+    T: AsRef<[u8]>,
+{
+    let bytes = bytes.as_ref();
+    if bytes.len() > limits.max_bytes {
+        return Err(Error::Image(ImageError::Limits(LimitError::from_kind(
+            LimitErrorKind::InsufficientMemory,
+        ))));
+    }
+
+    let mut image_limits = Limits::default();
+    image_limits.max_image_width = Some(limits.max_width);
+    image_limits.max_image_height = Some(limits.max_height);
+
+    let mut reader = Reader::new(Cursor::new(bytes)).with_guessed_format()?;
+    reader.limits(image_limits);
+
+    Ok(reader.decode()?)
+}
+
+/// Read the format and dimensions from the header of the image
+pub(crate) fn image_meta(bytes: &[u8]) -> Result<ImageMeta, Error> {
+    let reader = Reader::new(Cursor::new(bytes)).with_guessed_format()?;
+    let format = match reader.format() {
+        Some(format) => format,
+        None => {
+            return Err(Error::Image(ImageError::Unsupported(
+                UnsupportedError::from_format_and_kind(
+                    ImageFormatHint::Unknown,
+                    UnsupportedErrorKind::Format(ImageFormatHint::Unknown),
+                ),
+            )))
+        }
+    };
+    let (width, height) = reader.into_dimensions()?;
+
+    Ok(ImageMeta {
+        format,
+        width,
+        height,
+        byte_size: bytes.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::future;
+    use image::{ImageFormat, ImageOutputFormat, RgbImage};
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn decode_image() -> Result<(), Error> {
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::new(64, 32))
+            .write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png)?;
+
+        assert!(super::decode_image(&bytes, &ImageLimits::default()).is_ok());
+
+        let limits = ImageLimits {
+            max_width: 32,
+            ..Default::default()
+        };
+        assert!(matches!(
+            super::decode_image(&bytes, &limits),
+            Err(Error::Image(ImageError::Limits(_)))
+        ));
+
+        let limits = ImageLimits {
+            max_bytes: bytes.len() - 1,
+            ..Default::default()
+        };
+        assert!(matches!(
+            super::decode_image(&bytes, &limits),
+            Err(Error::Image(ImageError::Limits(_)))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn decode_image_blocking() -> Result<(), Error> {
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::new(256, 256))
+            .write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png)?;
+
+        let semaphore = Semaphore::new(2);
+        let images = future::try_join_all((0..50).map(|_| {
+            super::decode_image_blocking(bytes.clone(), ImageLimits::default(), &semaphore)
+        }))
+        .await?;
+
+        assert_eq!(images.len(), 50);
+        assert_eq!(semaphore.available_permits(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn image_meta() -> Result<(), Error> {
+        let mut bytes = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::new(64, 32))
+            .write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png)?;
+
+        assert_eq!(
+            super::image_meta(&bytes)?,
+            ImageMeta {
+                format: ImageFormat::Png,
+                width: 64,
+                height: 32,
+                byte_size: bytes.len(),
+            }
+        );
+
+        assert!(super::image_meta(b"not an image").is_err());
+
+        Ok(())
+    }
+}
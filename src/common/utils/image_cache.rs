@@ -0,0 +1,103 @@
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use boring::hash::{self, MessageDigest};
+use hex_simd::AsciiCase;
+use tokio::fs;
+use url::Url;
+use uuid::Uuid;
+
+use crate::Error;
+
+/// The path of the image in the filesystem image cache, the files are spread over
+/// subdirectories named after the first two hex digits of the SHA-256 of the url
+fn image_file_path(dir: &Path, url: &Url) -> Result<PathBuf, Error> {
+    let digest = hash::hash(MessageDigest::sha256(), url.as_str().as_bytes())?;
+    let name = hex_simd::encode_to_string(digest, AsciiCase::Lower);
+
+    Ok(dir.join(&name[..2]).join(name))
+}
+
+/// Read the image from the filesystem image cache, `None` if it is not cached
+pub(crate) async fn find_image_file(dir: &Path, url: &Url) -> Result<Option<Vec<u8>>, Error> {
+    match fs::read(image_file_path(dir, url)?).await {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error)?,
+    }
+}
+
+/// Write the image to the filesystem image cache, a partly written file is never visible
+pub(crate) async fn save_image_file<T>(dir: &Path, url: &Url, bytes: T) -> Result<(), Error>
+where
+    T: AsRef<[u8]>,
+{
+    let bytes = bytes.as_ref();
+    image::guess_format(bytes)?;
+
+    let path = image_file_path(dir, url)?;
+    fs::create_dir_all(path.parent().unwrap()).await?;
+
+    let temp_path = path.with_extension(format!("{}.tmp", Uuid::new_v4()));
+    fs::write(&temp_path, bytes).await?;
+    fs::rename(&temp_path, &path).await?;
+
+    Ok(())
+}
+
+/// The total size of the files in the filesystem image cache
+pub(crate) async fn image_cache_size(dir: &Path) -> Result<u64, Error> {
+    let mut size = 0;
+    let mut dirs = vec![dir.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        let mut entries = match fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == ErrorKind::NotFound => continue,
+            Err(error) => Err(error)?,
+        };
+
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            if metadata.is_dir() {
+                dirs.push(entry.path());
+            } else {
+                size += metadata.len();
+            }
+        }
+    }
+
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[tokio::test]
+    async fn image_file() -> Result<(), Error> {
+        let dir = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        let url = Url::parse("https://example.com/1.png")?;
+
+        assert_eq!(find_image_file(&dir, &url).await?, None);
+        assert_eq!(image_cache_size(&dir).await?, 0);
+        assert!(save_image_file(&dir, &url, b"not an image").await.is_err());
+
+        let mut bytes = Vec::new();
+        image::RgbImage::new(1, 1).write_to(
+            &mut std::io::Cursor::new(&mut bytes),
+            image::ImageOutputFormat::Png,
+        )?;
+        save_image_file(&dir, &url, &bytes).await?;
+        assert_eq!(image_cache_size(&dir).await?, bytes.len() as u64);
+        assert_eq!(find_image_file(&dir, &url).await?, Some(bytes));
+
+        fs::remove_dir_all(dir).await?;
+
+        Ok(())
+    }
+}
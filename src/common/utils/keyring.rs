@@ -1,4 +1,5 @@
 use keyring::Entry;
+use tokio::task;
 
 use crate::Error;
 
@@ -40,6 +41,25 @@ impl Keyring {
     }
 }
 
+/// Save the session of the user in the keyring on the blocking thread pool
+pub(crate) async fn save_session_to_keyring(
+    app_name: &'static str,
+    username: &str,
+    session: String,
+) -> Result<(), Error> {
+    let username = username.to_string();
+    task::spawn_blocking(move || Keyring::new(app_name, username)?.set_password(session)).await?
+}
+
+/// Load the session of the user from the keyring on the blocking thread pool
+pub(crate) async fn load_session_from_keyring(
+    app_name: &'static str,
+    username: &str,
+) -> Result<String, Error> {
+    let username = username.to_string();
+    task::spawn_blocking(move || Keyring::new(app_name, username)?.get_password()).await?
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -59,4 +79,18 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    #[cfg_attr(feature = "ci", ignore)]
+    async fn session() -> Result<(), Error> {
+        save_session_to_keyring("test", "test-session", String::from("session")).await?;
+        assert_eq!(
+            load_session_from_keyring("test", "test-session").await?,
+            "session"
+        );
+
+        Keyring::new("test", "test-session")?.delete_password()?;
+
+        Ok(())
+    }
 }
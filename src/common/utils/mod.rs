@@ -1,14 +1,24 @@
+mod decode;
 mod dir;
+mod image_cache;
 mod keyring;
+mod text;
 mod timing;
 mod uid;
 
+pub(crate) use self::decode::*;
+pub(crate) use self::image_cache::*;
+pub(crate) use self::text::*;
 pub(crate) use self::uid::*;
 
 pub use self::dir::*;
 pub use self::keyring::*;
 pub use self::timing::*;
 
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, Utc};
+
+use crate::Error;
+
 // TODO use https://doc.rust-lang.org/std/option/enum.Option.html#method.is_some_and
 #[must_use]
 #[inline]
@@ -22,8 +32,37 @@ where
     }
 }
 
+/// China Standard Time (UTC+8), which is used by both sites
+#[must_use]
+pub(crate) fn china_standard_time() -> Option<FixedOffset> {
+    FixedOffset::east_opt(8 * 60 * 60)
+}
+
+/// The date and time in China Standard Time of a Unix timestamp
+#[must_use]
+pub(crate) fn cst_from_timestamp(timestamp: i64) -> Option<NaiveDateTime> {
+    let date_time = DateTime::<Utc>::from_timestamp(timestamp, 0)?;
+    Some(
+        date_time
+            .with_timezone(&china_standard_time()?)
+            .naive_local(),
+    )
+}
+
+/// Today's date in China Standard Time (UTC+8), which is used by both sites
+pub(crate) fn today() -> Result<NaiveDate, Error> {
+    let offset = china_standard_time().ok_or_else(|| {
+        Error::NovelApi("The offset of China Standard Time is invalid".to_string())
+    })?;
+
+    Ok(Utc::now().with_timezone(&offset).date_naive())
+}
+
 #[cfg(test)]
 mod tests {
+    use chrono::NaiveDate;
+    use pretty_assertions::assert_eq;
+
     use crate::Error;
 
     #[test]
@@ -39,4 +78,28 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn today() -> Result<(), Error> {
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?;
+        let expected = super::cst_from_timestamp(now.as_secs() as i64).map(|time| time.date());
+        // The date may change between the two calls
+        assert!(expected <= Some(super::today()?));
+
+        Ok(())
+    }
+
+    #[test]
+    fn cst_from_timestamp() -> Result<(), Error> {
+        assert_eq!(
+            super::cst_from_timestamp(1_697_558_400),
+            NaiveDate::from_ymd_opt(2023, 10, 18).and_then(|date| date.and_hms_opt(0, 0, 0))
+        );
+        assert_eq!(
+            super::cst_from_timestamp(0),
+            NaiveDate::from_ymd_opt(1970, 1, 1).and_then(|date| date.and_hms_opt(8, 0, 0))
+        );
+
+        Ok(())
+    }
 }
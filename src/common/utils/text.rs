@@ -0,0 +1,268 @@
+use encoding_rs::Encoding;
+
+use crate::{CacheVerdict, ChapterInfo, ContentInfo, ContentInfos, Error};
+
+/// Decode the text with the charset of the `Content-Type` header, UTF-8 is assumed if there is no charset
+pub(crate) fn decode_text<T>(bytes: T, content_type: Option<&str>) -> Result<String, Error>
+where
+    T: AsRef<[u8]>,
+{
+    let bytes = bytes.as_ref();
+
+    let encoding = content_type
+        .and_then(charset)
+        .and_then(|charset| Encoding::for_label(charset.as_bytes()));
+
+    match encoding {
+        Some(encoding) if encoding != encoding_rs::UTF_8 => {
+            let (text, _, has_errors) = encoding.decode(bytes);
+            if has_errors {
+                return Err(Error::NovelApi(format!(
+                    "The response is not valid `{}`",
+                    encoding.name()
+                )));
+            }
+
+            Ok(text.into_owned())
+        }
+        _ => Ok(simdutf8::basic::from_utf8(bytes)?.to_string()),
+    }
+}
+
+fn charset(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+
+        if name.trim().eq_ignore_ascii_case("charset") {
+            Some(value.trim().trim_matches('"'))
+        } else {
+            None
+        }
+    })
+}
+
+/// Strip the leading UTF-8 BOM and convert CRLF/CR line endings to LF
+#[must_use]
+pub(crate) fn normalize_text<T>(text: T) -> String
+where
+    T: AsRef<str>,
+{
+    let text = text.as_ref();
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// Unescape the HTML entities commonly found in novel content
+#[must_use]
+pub(crate) fn unescape_html<T>(text: T) -> String
+where
+    T: AsRef<str>,
+{
+    text.as_ref()
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+/// Phrases that the sites put in the preview of a locked chapter
+const STUB_PHRASES: [&str; 6] = [
+    "订阅本章",
+    "购买本章",
+    "本章为VIP章节",
+    "本章为付费章节",
+    "登录后阅读",
+    "请先登录",
+];
+
+/// Content shorter than this number of characters may be a stub
+const STUB_MAX_CHARS: usize = 300;
+
+/// Whether the content is the preview stub of a locked chapter or is truncated,
+/// i.e. it is short and contains a known stub phrase,
+/// or it is far shorter than the word count of the chapter
+#[must_use]
+pub(crate) fn is_stub_content<T>(info: &ChapterInfo, content: T) -> bool
+where
+    T: AsRef<str>,
+{
+    let content = content.as_ref().trim();
+    let chars = content.chars().count();
+
+    if chars < STUB_MAX_CHARS && STUB_PHRASES.iter().any(|phrase| content.contains(phrase)) {
+        return true;
+    }
+
+    match info.word_count {
+        Some(word_count) => chars < STUB_MAX_CHARS && chars * 10 < word_count as usize,
+        None => false,
+    }
+}
+
+/// Return [`Error::ChapterLocked`] if the content fetched is a stub, so that it is not cached
+pub(crate) fn check_content<T>(info: &ChapterInfo, content: T) -> Result<(), Error>
+where
+    T: AsRef<str>,
+{
+    if is_stub_content(info, content) {
+        return Err(Error::ChapterLocked(info.title.clone()));
+    }
+
+    Ok(())
+}
+
+/// Allowed relative difference between the counted and the expected word count, the sites count a little differently
+const WORD_COUNT_TOLERANCE: f64 = 0.1;
+
+/// Compare the number of characters of the text content with the word count of the chapter
+pub(crate) fn verify_word_count(info: &ChapterInfo, content_infos: &ContentInfos) -> CacheVerdict {
+    let cached = content_infos
+        .iter()
+        .map(|content_info| match content_info {
+            ContentInfo::Text(text) => text.chars().filter(|c| !c.is_whitespace()).count(),
+            ContentInfo::Image(_) => 0,
+        })
+        .sum::<usize>();
+
+    match info.word_count {
+        Some(expected)
+            if (cached as f64 - expected as f64).abs() > expected as f64 * WORD_COUNT_TOLERANCE =>
+        {
+            CacheVerdict::Mismatch { cached, expected }
+        }
+        _ => CacheVerdict::Match,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use url::Url;
+
+    use crate::{CacheVerdict, ChapterInfo, ContentInfo, Error, Identifier};
+
+    #[test]
+    fn normalize_text() -> Result<(), Error> {
+        let text = "\u{feff}第一章\r\n  line 1\r\nline 2\rline 3\n";
+        let text = super::normalize_text(text);
+
+        assert_eq!(text, "第一章\n  line 1\nline 2\nline 3\n");
+        assert_eq!(
+            text.lines().collect::<Vec<_>>(),
+            vec!["第一章", "  line 1", "line 2", "line 3"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn decode_text() -> Result<(), Error> {
+        // "测试" encoded in GBK
+        let gbk = [0xB2, 0xE2, 0xCA, 0xD4];
+
+        assert_eq!(
+            super::decode_text(gbk, Some("application/json; charset=GBK"))?,
+            "测试"
+        );
+        assert_eq!(
+            super::decode_text(gbk, Some(r#"text/html;charset="gb18030""#))?,
+            "测试"
+        );
+        assert!(super::decode_text(gbk, Some("application/json")).is_err());
+        assert_eq!(super::decode_text("测试", None)?, "测试");
+
+        Ok(())
+    }
+
+    #[test]
+    fn unescape_html() -> Result<(), Error> {
+        assert_eq!(
+            super::unescape_html("&lt;p&gt;&quot;a&quot;&nbsp;&amp;lt;&#39;"),
+            "<p>\"a\" &lt;'"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn is_stub_content() -> Result<(), Error> {
+        let info = ChapterInfo {
+            identifier: Identifier::Id(1),
+            title: "第一章".to_string(),
+            is_vip: Some(true),
+            is_accessible: Some(true),
+            is_valid: Some(true),
+            word_count: Some(3000),
+            update_time: None,
+        };
+
+        let stub = "　　第一章的开头……\n\n本章为VIP章节，订阅本章后即可阅读全部内容。";
+        assert!(super::is_stub_content(&info, stub));
+        assert!(matches!(
+            super::check_content(&info, stub),
+            Err(Error::ChapterLocked(title)) if title == "第一章"
+        ));
+
+        let truncated = "　　第一章的开头……";
+        assert!(super::is_stub_content(&info, truncated));
+
+        let content = "　　正文".repeat(1000);
+        assert!(!super::is_stub_content(&info, &content));
+        assert!(super::check_content(&info, &content).is_ok());
+
+        let info = ChapterInfo {
+            word_count: None,
+            ..info
+        };
+        assert!(!super::is_stub_content(&info, truncated));
+
+        Ok(())
+    }
+
+    #[test]
+    fn verify_word_count() -> Result<(), Error> {
+        let info = ChapterInfo {
+            identifier: Identifier::Id(1),
+            title: "第一章".to_string(),
+            is_vip: None,
+            is_accessible: None,
+            is_valid: None,
+            word_count: Some(100),
+            update_time: None,
+        };
+        let content = |chars: usize| {
+            vec![
+                ContentInfo::Text("正".repeat(chars / 2)),
+                ContentInfo::Image(Url::parse("https://example.com/1.jpg").unwrap()),
+                ContentInfo::Text(format!("　　{}", "文".repeat(chars - chars / 2))),
+            ]
+        };
+
+        assert_eq!(
+            super::verify_word_count(&info, &content(95)),
+            CacheVerdict::Match
+        );
+        assert_eq!(
+            super::verify_word_count(&info, &content(50)),
+            CacheVerdict::Mismatch {
+                cached: 50,
+                expected: 100
+            }
+        );
+
+        let info = ChapterInfo {
+            word_count: None,
+            ..info
+        };
+        assert_eq!(
+            super::verify_word_count(&info, &content(50)),
+            CacheVerdict::Match
+        );
+
+        Ok(())
+    }
+}
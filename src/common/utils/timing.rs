@@ -1,4 +1,4 @@
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
 use crate::Error;
 
@@ -31,6 +31,15 @@ impl Timing {
         Ok(result)
     }
 
+    /// Get the time difference from the creation time as a [`Duration`], and reset the creation time to the current time
+    #[inline]
+    pub fn elapsed_duration(&mut self) -> Result<Duration, Error> {
+        let result = self.now.elapsed()?;
+        self.now = SystemTime::now();
+
+        Ok(result)
+    }
+
     #[inline]
     fn elapsed_str(&self) -> Result<String, Error> {
         let time = self.now.elapsed()?;
@@ -4,6 +4,8 @@ mod ciweimao;
 mod common;
 mod sfacg;
 
+pub mod prelude;
+
 pub use ciweimao::*;
 pub use common::*;
 pub use sfacg::*;
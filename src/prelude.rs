@@ -0,0 +1,8 @@
+//! Commonly used traits and types, `use novel_api::prelude::*;` imports all of them
+
+pub use crate::{
+    AuthorInfo, CacheSize, CacheVerdict, Category, ChapterInfo, ChapterPreview, ChapterVersion,
+    CiweimaoClient, Client, ContentInfo, ContentInfoResolved, ContentInfos, ContentInfosExt, Error,
+    Identifier, LoginRetry, NovelCard, NovelInfo, NovelReader, NovelsPage, Options, SfacgClient,
+    Tag, TagLogic, Toc, UserInfo, VolumeInfo, VolumeInfos, WordCountRange,
+};
@@ -2,19 +2,24 @@ mod structure;
 mod utils;
 
 use std::{
-    io::Cursor,
+    collections::HashMap,
     path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
 };
 
 use async_trait::async_trait;
-use image::{io::Reader, DynamicImage};
-use tokio::sync::OnceCell;
-use tracing::error;
+use chrono::NaiveDate;
+use image::DynamicImage;
+use tokio::sync::{OnceCell, Semaphore};
+use tracing::{error, warn};
 use url::Url;
 
 use crate::{
-    Category, ChapterInfo, Client, ContentInfo, ContentInfos, Error, FindImageResult,
-    FindTextResult, HTTPClient, Identifier, NovelDB, NovelInfo, Options, Tag, UserInfo, VolumeInfo,
+    AuthorInfo, CacheSize, CacheVerdict, Category, ChapterInfo, ChapterPreview, Client,
+    ContentInfo, ContentInfos, EndpointMetrics, Error, FindTextResult, HTTPClient, Identifier,
+    ImageLimits, LoginRetry, MetricsCollector, NovelDB, NovelInfo, NovelsPage, Options,
+    ResponseExt, SignInStatus, Tag, TagLogic, TlsVersion, Transport, UserInfo, VolumeInfo,
     VolumeInfos, WordCountRange,
 };
 use structure::*;
@@ -23,22 +28,48 @@ use structure::*;
 #[must_use]
 pub struct SfacgClient {
     proxy: Option<Url>,
+    image_proxy: Option<Url>,
     no_proxy: bool,
     cert_path: Option<PathBuf>,
+    pinned_cert: Option<Vec<u8>>,
+    min_tls_version: Option<TlsVersion>,
+    pool_idle_timeout: Option<Duration>,
+    reqwest_client: Option<reqwest::Client>,
+    login_retry: LoginRetry,
+    host: Option<Url>,
+    stale_ok: bool,
+    image_limits: ImageLimits,
+    image_decodes: Semaphore,
+    write_behind_images: bool,
+    no_cache: bool,
+    image_cache_dir: Option<PathBuf>,
+    transport: Option<Arc<dyn Transport>>,
+    metrics: Option<Arc<MetricsCollector>>,
 
     client: OnceCell<HTTPClient>,
     client_rss: OnceCell<HTTPClient>,
 
     db: OnceCell<NovelDB>,
+
+    time_offset: OnceCell<i64>,
+    categories: OnceCell<Vec<Category>>,
+    tags: OnceCell<Vec<Tag>>,
+    taxonomy: OnceCell<Vec<(Category, Vec<Tag>)>>,
 }
 
 #[async_trait]
 impl Client for SfacgClient {
     fn proxy(&mut self, proxy: Url) {
         self.proxy = Some(proxy);
+        self.no_proxy = false;
+    }
+
+    fn image_proxy(&mut self, proxy: Url) {
+        self.image_proxy = Some(proxy);
     }
 
     fn no_proxy(&mut self) {
+        self.proxy = None;
         self.no_proxy = true;
     }
 
@@ -49,103 +80,251 @@ impl Client for SfacgClient {
         self.cert_path = Some(cert_path.as_ref().to_path_buf());
     }
 
+    fn pin_cert(&mut self, der: &[u8]) {
+        self.pinned_cert = Some(der.to_vec());
+    }
+
+    fn min_tls_version(&mut self, version: TlsVersion) {
+        self.min_tls_version = Some(version);
+    }
+
+    fn login_retry(&mut self, policy: LoginRetry) {
+        self.login_retry = policy;
+    }
+
+    fn reqwest_client(&mut self, client: reqwest::Client) {
+        self.reqwest_client = Some(client);
+    }
+
+    fn pool_idle_timeout(&mut self, timeout: Duration) {
+        self.pool_idle_timeout = Some(timeout);
+    }
+
+    fn host(&mut self, host: Url) {
+        self.host = Some(host);
+    }
+
+    fn stale_ok(&mut self, flag: bool) {
+        self.stale_ok = flag;
+    }
+
+    fn enable_metrics(&mut self) {
+        self.metrics = Some(Arc::new(MetricsCollector::default()));
+    }
+
+    fn metrics(&self) -> HashMap<String, EndpointMetrics> {
+        self.metrics
+            .as_ref()
+            .map(|metrics| metrics.snapshot())
+            .unwrap_or_default()
+    }
+
+    fn image_limits(&mut self, limits: ImageLimits) {
+        self.image_limits = limits;
+    }
+
+    fn max_image_decodes(&mut self, count: usize) {
+        self.image_decodes = Semaphore::new(count.max(1));
+    }
+
+    fn write_behind_images(&mut self, flag: bool) {
+        self.write_behind_images = flag;
+    }
+
+    fn image_cache_fs(&mut self, dir: PathBuf) {
+        self.image_cache_dir = Some(dir);
+    }
+
+    fn no_cache(&mut self) {
+        self.no_cache = true;
+    }
+
+    fn site_name(&self) -> &'static str {
+        SfacgClient::APP_NAME
+    }
+
+    fn novel_url(&self, id: u32) -> Url {
+        Url::parse(&format!("https://book.sfacg.com/Novel/{id}/")).unwrap()
+    }
+
     async fn shutdown(&self) -> Result<(), Error> {
+        self.flush().await?;
         self.client().await?.shutdown()
     }
 
+    async fn flush(&self) -> Result<(), Error> {
+        match self.db.get() {
+            Some(db) => db.flush().await,
+            None => Ok(()),
+        }
+    }
+
+    async fn reset_connections(&self) -> Result<(), Error> {
+        if let Some(client) = self.client.get() {
+            client.reset().await?;
+        }
+
+        if let Some(client_rss) = self.client_rss.get() {
+            client_rss.reset().await?;
+        }
+
+        Ok(())
+    }
+
+    async fn cache_size(&self) -> Result<CacheSize, Error> {
+        crate::cache_size(self.db().await?, self.image_cache_dir.as_deref()).await
+    }
+
+    async fn export_cache(&self, out: &Path) -> Result<(), Error> {
+        crate::export_cache(SfacgClient::APP_NAME, self.db().await?, out).await
+    }
+
+    async fn import_cache(&self, archive: &Path, force: bool) -> Result<(), Error> {
+        if self.db.initialized() {
+            return Err(Error::NovelApi(
+                "The cache is in use and can not be overwritten".to_string(),
+            ));
+        }
+
+        crate::import_cache(SfacgClient::APP_NAME, archive, force).await
+    }
+
     async fn add_cookie(&self, cookie_str: &str, url: &Url) -> Result<(), Error> {
         Ok(self.client().await?.add_cookie(cookie_str, url)?)
     }
 
+    async fn export_session(&self) -> Result<String, Error> {
+        self.client().await?.export_cookies()
+    }
+
+    async fn import_session(&self, session: &str) -> Result<(), Error> {
+        self.client().await?.import_cookies(session)
+    }
+
+    async fn login_saving_to_keyring<T, E>(&self, username: T, password: E) -> Result<(), Error>
+    where
+        T: AsRef<str> + Send + Sync,
+        E: AsRef<str> + Send + Sync,
+    {
+        self.login(username.as_ref(), password).await?;
+
+        crate::save_session_to_keyring(
+            SfacgClient::APP_NAME,
+            username.as_ref(),
+            self.export_session().await?,
+        )
+        .await
+    }
+
+    async fn login_from_keyring<T>(&self, username: T) -> Result<(), Error>
+    where
+        T: AsRef<str> + Send + Sync,
+    {
+        let session =
+            crate::load_session_from_keyring(SfacgClient::APP_NAME, username.as_ref()).await?;
+        self.import_session(&session).await
+    }
+
     async fn login<T, E>(&self, username: T, password: E) -> Result<(), Error>
     where
         T: AsRef<str> + Send + Sync,
         E: AsRef<str> + Send + Sync,
     {
+        self.login_retry
+            .run(|| self.login_once(username.as_ref(), password.as_ref()))
+            .await
+    }
+
+    async fn user_info(&self) -> Result<Option<UserInfo>, Error> {
         let response = self
-            .post(
-                "/sessions",
-                &LoginRequest {
-                    user_name: username.as_ref().to_string(),
-                    pass_word: password.as_ref().to_string(),
-                },
-            )
+            .get("/user")
             .await?
-            .json::<LoginResponse>()
+            .json_decoded::<UserResponse>()
             .await?;
+        if response.status.unauthorized() {
+            return Ok(None);
+        }
         response.status.check()?;
 
-        // TODO Is it really necessary?
+        let user_info = UserInfo {
+            nickname: crate::check_data(response.data)?
+                .nick_name
+                .trim()
+                .to_string(),
+        };
+
+        Ok(Some(user_info))
+    }
+
+    async fn sign_in_status(&self) -> Result<SignInStatus, Error> {
         let response = self
-            .get("/position")
+            .get("/user/newSignInfo")
             .await?
-            .json::<PositionResponse>()
+            .json_decoded::<SignInfoResponse>()
             .await?;
+        if response.status.unauthorized() {
+            return Err(Error::NotLoggedIn);
+        }
         response.status.check()?;
 
-        Ok(())
+        let today = crate::today()?;
+        let is_signed_in = crate::check_data(response.data)?
+            .iter()
+            .any(|data| NaiveDate::from_ymd_opt(data.year, data.month, data.day) == Some(today));
+
+        Ok(SignInStatus { is_signed_in })
     }
 
-    async fn user_info(&self) -> Result<Option<UserInfo>, Error> {
-        let response = self.get("/user").await?.json::<UserResponse>().await?;
-        if response.status.unauthorized() {
-            return Ok(None);
+    async fn sign_in(&self) -> Result<(), Error> {
+        if self.sign_in_status().await?.is_signed_in {
+            return Err(Error::AlreadySignedIn);
         }
-        response.status.check()?;
 
-        let user_info = UserInfo {
-            nickname: response.data.unwrap().nick_name.trim().to_string(),
-        };
+        let response = self
+            .put(
+                "/user/newSignInfo",
+                &SignInRequest {
+                    sign_date: crate::today()?.format("%Y-%m-%d").to_string(),
+                },
+            )
+            .await?
+            .json_decoded::<SignInResponse>()
+            .await?;
+        response.status.check()?;
 
-        Ok(Some(user_info))
+        Ok(())
     }
 
     async fn novel_info(&self, id: u32) -> Result<Option<NovelInfo>, Error> {
-        assert!(id <= i32::MAX as u32);
+        self.novel_info_with_expand::<&str>(id, &[]).await
+    }
 
+    async fn author_info(&self, author_id: u32) -> Result<AuthorInfo, Error> {
         let response = self
             .get_query(
-                format!("/novels/{id}"),
-                &NovelInfoRequest {
-                    expand: "intro,typeName,sysTags",
+                format!("/users/{author_id}"),
+                &AuthorInfoRequest {
+                    expand: "introduction,novelCount",
                 },
             )
             .await?
-            .json::<NovelInfoResponse>()
+            .json_decoded::<AuthorInfoResponse>()
             .await?;
-        if response.status.not_found() {
-            return Ok(None);
-        }
         response.status.check()?;
 
-        let novel_data = response.data.unwrap();
-
-        let word_count = if novel_data.char_count <= 0 {
-            None
-        } else {
-            Some(novel_data.char_count as u32)
-        };
-
-        let category = Category {
-            id: Some(novel_data.type_id),
-            name: novel_data.expand.type_name.trim().to_string(),
-        };
-
-        let novel_info = NovelInfo {
-            id,
-            name: novel_data.novel_name.trim().to_string(),
-            author_name: novel_data.author_name.trim().to_string(),
-            cover_url: Some(novel_data.novel_cover),
-            introduction: SfacgClient::parse_intro(novel_data.expand.intro),
-            word_count,
-            is_finished: Some(novel_data.is_finish),
-            create_time: Some(novel_data.add_time),
-            update_time: Some(novel_data.last_update_time),
-            category: Some(category),
-            tags: SfacgClient::parse_tags(novel_data.expand.sys_tags),
-        };
+        let data = crate::check_data(response.data)?;
+        let introduction = data.expand.introduction.trim();
 
-        Ok(Some(novel_info))
+        Ok(AuthorInfo {
+            name: data.nick_name.trim().to_string(),
+            avatar_url: Url::parse(data.avatar.trim()).ok(),
+            introduction: if introduction.is_empty() {
+                None
+            } else {
+                Some(introduction.to_string())
+            },
+            novel_count: data.expand.novel_count,
+        })
     }
 
     async fn volume_infos(&self, id: u32) -> Result<VolumeInfos, Error> {
@@ -154,14 +333,16 @@ impl Client for SfacgClient {
         let response = self
             .get(format!("/novels/{id}/dirs"))
             .await?
-            .json::<NovelsDirsResponse>()
+            .json_decoded::<NovelsDirsResponse>()
             .await?;
         response.status.check()?;
 
         let mut volumes = VolumeInfos::new();
-        for volume in response.data.unwrap().volume_list {
+        for volume in crate::check_data(response.data)?.volume_list {
             let mut volume_info = VolumeInfo {
                 title: volume.title.trim().to_string(),
+                description: None,
+                cover_url: None,
                 chapter_infos: vec![],
             };
 
@@ -169,13 +350,12 @@ impl Client for SfacgClient {
                 let update_time = if chapter.update_time.is_some() {
                     chapter.update_time
                 } else {
-                    Some(chapter.add_time)
+                    chapter.add_time
                 };
 
-                let word_count = if chapter.char_count <= 0 {
-                    None
-                } else {
-                    Some(chapter.char_count as u16)
+                let word_count = match chapter.char_count {
+                    Some(char_count) if char_count > 0 => Some(char_count as u16),
+                    _ => None,
                 };
 
                 let chapter_info = ChapterInfo {
@@ -183,8 +363,8 @@ impl Client for SfacgClient {
                     title: chapter.title.trim().to_string(),
                     word_count,
                     update_time,
-                    is_vip: Some(chapter.is_vip),
-                    is_accessible: Some(chapter.need_fire_money == 0),
+                    is_vip: chapter.is_vip,
+                    is_accessible: chapter.need_fire_money.map(|money| money == 0),
                     is_valid: None,
                 };
 
@@ -198,6 +378,10 @@ impl Client for SfacgClient {
     }
 
     async fn content_infos(&self, info: &ChapterInfo) -> Result<ContentInfos, Error> {
+        if self.no_cache {
+            return Ok(SfacgClient::parse_content(self.fetch_content(info).await?));
+        }
+
         let content;
 
         match self.db().await?.find_text(info).await? {
@@ -205,17 +389,26 @@ impl Client for SfacgClient {
                 content = str;
             }
             other => {
-                let response = self
-                    .get_query(
-                        format!("/Chaps/{}", info.identifier.to_string()),
-                        &ChapsRequest { expand: "content" },
-                    )
-                    .await?
-                    .json::<ChapsResponse>()
-                    .await?;
-                response.status.check()?;
-
-                content = response.data.unwrap().expand.content;
+                match self.fetch_content(info).await {
+                    Ok(str) => content = str,
+                    Err(error)
+                        if self.stale_ok
+                            && matches!(other, FindTextResult::Outdate)
+                            && error.is_retryable() =>
+                    {
+                        match self.db().await?.find_stale_text(info).await? {
+                            Some(str) => {
+                                warn!(
+                                    "Failed to download chapter: `{}`, use the outdated cache, {error}",
+                                    info.title
+                                );
+                                return Ok(SfacgClient::parse_content(str));
+                            }
+                            None => return Err(error),
+                        }
+                    }
+                    Err(error) => return Err(error),
+                }
 
                 match other {
                     FindTextResult::None => self.db().await?.insert_text(info, &content).await?,
@@ -225,38 +418,114 @@ impl Client for SfacgClient {
             }
         }
 
-        let mut content_infos = ContentInfos::new();
-        for line in content
-            .lines()
-            .map(|line| line.trim())
-            .filter(|line| !line.is_empty())
-        {
-            if line.starts_with("[img") {
-                if let Some(url) = SfacgClient::parse_image_url(line) {
-                    content_infos.push(ContentInfo::Image(url));
+        Ok(SfacgClient::parse_content(content))
+    }
+
+    async fn chapter_preview(&self, info: &ChapterInfo) -> Result<Option<ChapterPreview>, Error> {
+        if info.can_download() {
+            match self.content_infos(info).await {
+                Ok(content_infos) => {
+                    return Ok(Some(ChapterPreview {
+                        content_infos,
+                        is_preview: false,
+                    }))
                 }
-            } else {
-                content_infos.push(ContentInfo::Text(line.to_string()));
+                Err(Error::ChapterLocked(_)) => (),
+                Err(error) => return Err(error),
+            }
+        }
+
+        let cached = if self.no_cache {
+            None
+        } else {
+            self.db().await?.find_preview(info).await?
+        };
+
+        let content = match cached {
+            Some(str) => str,
+            None => {
+                let str = self.fetch_raw_content(info).await?;
+                if str.trim().is_empty() {
+                    return Ok(None);
+                }
+
+                if !self.no_cache {
+                    self.db().await?.save_preview(info, &str).await?;
+                }
+                str
             }
+        };
+
+        Ok(Some(ChapterPreview {
+            content_infos: SfacgClient::parse_content(content),
+            is_preview: true,
+        }))
+    }
+
+    async fn is_content_cached(&self, info: &ChapterInfo) -> Result<bool, Error> {
+        Ok(matches!(
+            self.db().await?.find_text(info).await?,
+            FindTextResult::Ok(_)
+        ))
+    }
+
+    async fn verify_cached_chapter(&self, info: &ChapterInfo) -> Result<CacheVerdict, Error> {
+        match self.db().await?.find_text(info).await? {
+            FindTextResult::Ok(str) => Ok(crate::verify_word_count(
+                info,
+                &SfacgClient::parse_content(str),
+            )),
+            FindTextResult::None | FindTextResult::Outdate => Ok(CacheVerdict::NotCached),
         }
+    }
 
-        Ok(content_infos)
+    async fn clear_chapters_cache(&self, identifiers: &[Identifier]) -> Result<u64, Error> {
+        self.db().await?.delete_texts(identifiers).await
+    }
+
+    async fn cache_content(&self, info: &ChapterInfo, text: &str) -> Result<(), Error> {
+        self.db().await?.save_text(info, text).await
+    }
+
+    async fn cache_image(&self, url: &Url, bytes: &[u8]) -> Result<(), Error> {
+        match &self.image_cache_dir {
+            Some(dir) => crate::save_image_file(dir, url, bytes).await,
+            None => self.db().await?.save_image(url, bytes).await,
+        }
     }
 
     async fn image(&self, url: &Url) -> Result<DynamicImage, Error> {
-        match self.db().await?.find_image(url).await? {
-            FindImageResult::Ok(image) => Ok(image),
-            FindImageResult::None => {
-                let response = self.get_rss(url).await?;
-                let bytes = response.bytes().await?;
+        let bytes = self.image_bytes(url).await?;
+        crate::decode_image_blocking(bytes, self.image_limits, &self.image_decodes).await
+    }
+
+    async fn image_bytes(&self, url: &Url) -> Result<Vec<u8>, Error> {
+        if self.no_cache {
+            return self.fetch_image_bytes(url).await;
+        }
 
-                let image = Reader::new(Cursor::new(&bytes))
-                    .with_guessed_format()?
-                    .decode()?;
+        let cached = match &self.image_cache_dir {
+            Some(dir) => crate::find_image_file(dir, url).await?,
+            None => self.db().await?.find_image_bytes(url).await?,
+        };
 
-                self.db().await?.insert_image(url, bytes).await?;
+        match cached {
+            Some(bytes) => Ok(bytes),
+            None => {
+                let bytes = self.fetch_image_bytes(url).await?;
+
+                if let Some(dir) = &self.image_cache_dir {
+                    crate::save_image_file(dir, url, &bytes).await?;
+                } else if self.write_behind_images {
+                    self.db()
+                        .await?
+                        .insert_image_behind(url, bytes.clone())
+                        .await;
+                } else {
+                    self.db().await?.insert_image(url, &bytes).await?;
+                }
 
-                Ok(image)
+                Ok(bytes)
             }
         }
     }
@@ -265,29 +534,7 @@ impl Client for SfacgClient {
     where
         T: AsRef<str> + Send + Sync,
     {
-        let response = self
-            .get_query(
-                "/search/novels/result/new",
-                &SearchRequest {
-                    page,
-                    q: text.as_ref().to_string(),
-                    size,
-                    sort: "hot",
-                },
-            )
-            .await?
-            .json::<SearchResponse>()
-            .await?;
-        response.status.check()?;
-
-        let mut result = Vec::new();
-        if response.data.is_some() {
-            for novel_info in response.data.unwrap().novels {
-                result.push(novel_info.novel_id);
-            }
-        }
-
-        Ok(result)
+        self.search_infos_sorted(text, page, size, "hot").await
     }
 
     async fn bookshelf_infos(&self) -> Result<Vec<u32>, Error> {
@@ -299,7 +546,7 @@ impl Client for SfacgClient {
                 },
             )
             .await?
-            .json::<FavoritesResponse>()
+            .json_decoded::<FavoritesResponse>()
             .await?;
         response.status.check()?;
 
@@ -318,20 +565,18 @@ impl Client for SfacgClient {
     }
 
     async fn categories(&self) -> Result<&Vec<Category>, Error> {
-        static CATEGORIES: OnceCell<Vec<Category>> = OnceCell::const_new();
-
-        CATEGORIES
+        self.categories
             .get_or_try_init(|| async {
                 let response = self
                     .get("/noveltypes")
                     .await?
-                    .json::<CategoryResponse>()
+                    .json_decoded::<CategoryResponse>()
                     .await?;
                 response.status.check()?;
 
                 let mut result = Vec::new();
 
-                for tag_data in response.data.unwrap() {
+                for tag_data in crate::check_data(response.data)? {
                     result.push(Category {
                         id: Some(tag_data.type_id),
                         name: tag_data.type_name.trim().to_string(),
@@ -344,45 +589,56 @@ impl Client for SfacgClient {
     }
 
     async fn tags(&self) -> Result<&Vec<Tag>, Error> {
-        static TAGS: OnceCell<Vec<Tag>> = OnceCell::const_new();
-
-        TAGS.get_or_try_init(|| async {
-            let response = self
-                .get("/novels/0/sysTags")
-                .await?
-                .json::<TagResponse>()
-                .await?;
-            response.status.check()?;
-
-            let mut result = Vec::new();
+        self.tags
+            .get_or_try_init(|| async {
+                let mut result = self.category_tags(0).await?;
 
-            for tag_data in response.data.unwrap() {
+                // Tag that have been removed, but can still be used
                 result.push(Tag {
-                    id: Some(tag_data.sys_tag_id),
-                    name: tag_data.tag_name.trim().to_string(),
+                    id: Some(74),
+                    name: "百合".to_string(),
                 });
-            }
 
-            // Tag that have been removed, but can still be used
-            result.push(Tag {
-                id: Some(74),
-                name: "百合".to_string(),
-            });
+                Ok(result)
+            })
+            .await
+    }
 
-            Ok(result)
-        })
-        .await
+    async fn tags_for_category(&self, category: &Category) -> Result<Vec<Tag>, Error> {
+        match category.id {
+            Some(id) => self.category_tags(id).await,
+            None => Ok(self.tags().await?.clone()),
+        }
     }
 
-    async fn novels(&self, option: &Options, page: u16, size: u16) -> Result<Vec<u32>, Error> {
+    async fn browse_taxonomy(&self) -> Result<&Vec<(Category, Vec<Tag>)>, Error> {
+        self.taxonomy
+            .get_or_try_init(|| crate::fetch_taxonomy(self))
+            .await
+    }
+
+    async fn novels_page(
+        &self,
+        option: &Options,
+        page: u16,
+        size: u16,
+    ) -> Result<NovelsPage, Error> {
         let mut category_id = 0;
         if option.category.is_some() {
             category_id = option.category.as_ref().unwrap().id.unwrap();
         }
 
+        let sort = match &option.sort {
+            Some(sort) => SfacgClient::check_sort(sort)?,
+            None => "viewtimes",
+        };
+
         let is_finish = SfacgClient::bool_to_str(&option.is_finished);
         let is_free = SfacgClient::bool_to_str(&option.is_vip.as_ref().map(|x| !x));
 
+        // sfacg only returns novels that have all the included tags
+        option.check_tag_logic(TagLogic::And)?;
+        SfacgClient::check_tag_count(option)?;
         let sys_tag_ids = SfacgClient::tag_ids(&option.tags);
         let not_exclude_sys_tag_ids = SfacgClient::tag_ids(&option.excluded_tags);
 
@@ -413,34 +669,394 @@ impl Client for SfacgClient {
                     not_exclude_sys_tag_ids,
                     updatedays: option.update_days,
                     page,
-                    size,
-                    sort: "viewtimes",
+                    size: crate::page_size(size, SfacgClient::MAX_PAGE_SIZE),
+                    sort,
                 },
             )
             .await?
-            .json::<NovelsResponse>()
+            .json_decoded::<NovelsResponse>()
             .await?;
         response.status.check()?;
 
-        let mut result = Vec::new();
+        let mut ids = Vec::new();
         if response.data.is_some() {
             for novel_data in response.data.unwrap() {
-                result.push(novel_data.novel_id);
+                ids.push(novel_data.novel_id);
             }
         }
 
-        Ok(result)
+        Ok(NovelsPage {
+            ids,
+            total: response.total,
+        })
     }
 }
 
 impl SfacgClient {
-    fn parse_tags(sys_tags: Vec<NovelInfoSysTag>) -> Option<Vec<Tag>> {
-        let mut result = vec![];
-        for tag in sys_tags {
-            result.push(Tag {
-                id: Some(tag.sys_tag_id),
-                name: tag.tag_name.trim().to_string(),
-            });
+    const NOVEL_INFO_EXPAND: &str = "intro,typeName,sysTags,fav";
+
+    /// The maximum number of tags that can be included in [`crate::Options::tags`]
+    pub const MAX_TAGS: usize = 5;
+    /// The maximum number of tags that can be excluded in [`crate::Options::excluded_tags`]
+    pub const MAX_EXCLUDED_TAGS: usize = 5;
+
+    /// Sort tokens accepted by the search and [`crate::Options::sort`]
+    pub const SORTS: [&'static str; 5] =
+        ["hot", "viewtimes", "latest", "bookmark", "typelastupdate"];
+
+    /// Search with the sort token, one of [`SfacgClient::SORTS`], return novel id
+    pub async fn search_infos_sorted<T>(
+        &self,
+        text: T,
+        page: u16,
+        size: u16,
+        sort: &str,
+    ) -> Result<Vec<u32>, Error>
+    where
+        T: AsRef<str> + Send + Sync,
+    {
+        let response = self
+            .get_query(
+                "/search/novels/result/new",
+                &SfacgClient::search_request(text, page, size, sort, None)?,
+            )
+            .await?
+            .json_decoded::<SearchResponse>()
+            .await?;
+        response.status.check()?;
+
+        let mut result = Vec::new();
+        if response.data.is_some() {
+            for novel_info in response.data.unwrap().novels {
+                result.push(novel_info.novel_id);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Search with the sort token, one of [`SfacgClient::SORTS`], return the novel information
+    ///
+    /// The information comes with the search result, so there is no request per novel as with [`Client::novel_info`]
+    pub async fn search_novels<T>(
+        &self,
+        text: T,
+        page: u16,
+        size: u16,
+        sort: &str,
+    ) -> Result<Vec<NovelInfo>, Error>
+    where
+        T: AsRef<str> + Send + Sync,
+    {
+        let response = self
+            .get_query(
+                "/search/novels/result/new",
+                &SfacgClient::search_request(
+                    text,
+                    page,
+                    size,
+                    sort,
+                    Some(SfacgClient::NOVEL_INFO_EXPAND),
+                )?,
+            )
+            .await?
+            .json_decoded::<SearchNovelsResponse>()
+            .await?;
+        response.status.check()?;
+
+        let mut result = Vec::new();
+        if response.data.is_some() {
+            for novel_data in response.data.unwrap().novels {
+                result.push(SfacgClient::parse_novel_info(
+                    novel_data.novel_id,
+                    novel_data.info,
+                ));
+            }
+        }
+
+        Ok(result)
+    }
+
+    fn search_request<T>(
+        text: T,
+        page: u16,
+        size: u16,
+        sort: &str,
+        expand: Option<&'static str>,
+    ) -> Result<SearchRequest, Error>
+    where
+        T: AsRef<str>,
+    {
+        Ok(SearchRequest {
+            page,
+            q: text.as_ref().to_string(),
+            size: crate::page_size(size, SfacgClient::MAX_PAGE_SIZE),
+            sort: SfacgClient::check_sort(sort)?,
+            expand,
+        })
+    }
+
+    async fn category_tags(&self, category_id: u16) -> Result<Vec<Tag>, Error> {
+        let response = self
+            .get(format!("/novels/{category_id}/sysTags"))
+            .await?
+            .json_decoded::<TagResponse>()
+            .await?;
+        response.status.check()?;
+
+        let mut result = Vec::new();
+
+        for tag_data in crate::check_data(response.data)? {
+            result.push(Tag {
+                id: Some(tag_data.sys_tag_id),
+                name: tag_data.tag_name.trim().to_string(),
+            });
+        }
+
+        Ok(result)
+    }
+
+    async fn login_once(&self, username: &str, password: &str) -> Result<(), Error> {
+        let response = self
+            .post(
+                "/sessions",
+                &LoginRequest {
+                    user_name: username.to_string(),
+                    pass_word: password.to_string(),
+                },
+            )
+            .await?
+            .json_decoded::<LoginResponse>()
+            .await?;
+        response.status.check_login()?;
+
+        // TODO Is it really necessary?
+        self.init_session().await
+    }
+
+    /// Perform the handshake that `login` ends with, so that the session cookie is
+    /// established before the first request, it does not require logging in
+    pub async fn init_session(&self) -> Result<(), Error> {
+        let response = self
+            .get("/position")
+            .await?
+            .json_decoded::<PositionResponse>()
+            .await?;
+        response.status.check()?;
+
+        Ok(())
+    }
+
+    /// Send an authenticated GET request and return the body before JSON deserialization,
+    /// which is useful for reporting api changes with the actual payload
+    pub async fn get_raw<T>(&self, url: T) -> Result<String, Error>
+    where
+        T: AsRef<str>,
+    {
+        self.get(url).await?.text_decoded().await
+    }
+
+    /// Get novel information, requesting extra `expand` fields in addition to the default ones
+    ///
+    /// Known fields are parsed into [`NovelInfo`] when present, e.g. `fav` is parsed into `favorite_count`
+    /// and `chapterCount` into `chapter_count`
+    pub async fn novel_info_with_expand<T>(
+        &self,
+        id: u32,
+        extra_expand: &[T],
+    ) -> Result<Option<NovelInfo>, Error>
+    where
+        T: AsRef<str>,
+    {
+        assert!(id <= i32::MAX as u32);
+
+        let mut expand = String::from(SfacgClient::NOVEL_INFO_EXPAND);
+        for field in extra_expand {
+            expand.push(',');
+            expand.push_str(field.as_ref().trim());
+        }
+
+        let response = self
+            .get_query(format!("/novels/{id}"), &NovelInfoRequest { expand })
+            .await?
+            .json_decoded::<NovelInfoResponse>()
+            .await?;
+        if response.status.not_found() {
+            return Ok(None);
+        }
+        response.status.check()?;
+
+        Ok(Some(SfacgClient::parse_novel_info(
+            id,
+            crate::check_data(response.data)?,
+        )))
+    }
+
+    fn parse_novel_info(id: u32, novel_data: NovelInfoData) -> NovelInfo {
+        let word_count = if novel_data.char_count <= 0 {
+            None
+        } else {
+            Some(novel_data.char_count as u32)
+        };
+
+        let category = Category {
+            id: Some(novel_data.type_id),
+            name: novel_data.expand.type_name.trim().to_string(),
+        };
+
+        // All tags returned by sfacg are system tags
+        let tags = SfacgClient::parse_tags(novel_data.expand.sys_tags);
+
+        NovelInfo {
+            id,
+            name: novel_data.novel_name.trim().to_string(),
+            author_name: novel_data.author_name.trim().to_string(),
+            author_id: novel_data.author_id,
+            cover_url: Some(novel_data.novel_cover),
+            introduction: SfacgClient::parse_intro(novel_data.expand.intro),
+            word_count,
+            is_finished: Some(novel_data.is_finish),
+            create_time: Some(novel_data.add_time),
+            update_time: Some(novel_data.last_update_time),
+            category: Some(category),
+            all_tags: tags.clone(),
+            tags,
+            view_count: novel_data.view_times,
+            favorite_count: novel_data.expand.fav,
+            rating: novel_data.point,
+            chapter_count: novel_data.expand.chapter_count,
+        }
+    }
+
+    /// Upload the reading progress of the novel, so that the official app continues from `chapter`
+    ///
+    /// The server keeps only the latest progress, so sending the same progress again has no effect
+    pub async fn update_reading_progress(
+        &self,
+        novel_id: u32,
+        chapter: &Identifier,
+    ) -> Result<(), Error> {
+        let chap_id = match chapter {
+            Identifier::Id(id) => *id,
+            Identifier::Url(url) => {
+                return Err(Error::NovelApi(format!(
+                    "sfacg chapter identifier must be an id: `{url}`"
+                )))
+            }
+        };
+
+        let response = self
+            .put(
+                format!("/user/novels/{novel_id}/readprogress"),
+                &ReadingProgressRequest { chap_id },
+            )
+            .await?
+            .json_decoded::<ReadingProgressResponse>()
+            .await?;
+        if response.status.unauthorized() {
+            return Err(Error::NotLoggedIn);
+        }
+        response.status.check()?;
+
+        Ok(())
+    }
+
+    /// Get the url of the official audio of the chapter, `None` if the chapter has no audio
+    pub async fn chapter_audio(&self, info: &ChapterInfo) -> Result<Option<Url>, Error> {
+        let response = self
+            .get_query(
+                format!("/Chaps/{}", info.identifier),
+                &ChapsRequest {
+                    expand: "audioUrl",
+                    page: None,
+                },
+            )
+            .await?
+            .json_decoded::<ChapsAudioResponse>()
+            .await?;
+        response.status.check()?;
+
+        match crate::check_data(response.data)?.expand.audio_url {
+            Some(url) if !url.trim().is_empty() => Ok(Some(Url::parse(url.trim())?)),
+            _ => Ok(None),
+        }
+    }
+
+    async fn fetch_image_bytes(&self, url: &Url) -> Result<Vec<u8>, Error> {
+        let bytes = self.get_rss(url).await?.bytes().await?;
+        image::guess_format(&bytes)?;
+
+        Ok(bytes.to_vec())
+    }
+
+    async fn fetch_content(&self, info: &ChapterInfo) -> Result<String, Error> {
+        let content = self.fetch_raw_content(info).await?;
+        crate::check_content(info, &content)?;
+
+        Ok(content)
+    }
+
+    /// Download the content without checking whether it is the preview of a locked chapter
+    ///
+    /// Long chapters may be split into pages by the server, the pages are joined into one text
+    async fn fetch_raw_content(&self, info: &ChapterInfo) -> Result<String, Error> {
+        let mut pages = Vec::new();
+
+        loop {
+            if pages.len() >= SfacgClient::MAX_CONTENT_PAGES {
+                return Err(Error::NovelApi(format!(
+                    "The chapter has more than {} pages: `{}`",
+                    SfacgClient::MAX_CONTENT_PAGES,
+                    info.title
+                )));
+            }
+
+            let response = self
+                .get_query(
+                    format!("/Chaps/{}", info.identifier),
+                    &ChapsRequest {
+                        expand: "content",
+                        page: (!pages.is_empty()).then_some(pages.len() as u16),
+                    },
+                )
+                .await?
+                .json_decoded::<ChapsResponse>()
+                .await?;
+            response.status.check()?;
+
+            let expand = crate::check_data(response.data)?.expand;
+            pages.push(expand.content);
+
+            if !expand.has_next_page {
+                break;
+            }
+        }
+
+        Ok(pages.join("\n"))
+    }
+
+    fn parse_content(content: String) -> ContentInfos {
+        let content = SfacgClient::clean_content(crate::normalize_text(content));
+
+        let mut content_infos = ContentInfos::new();
+        for line in content
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+        {
+            SfacgClient::parse_line(line, &mut content_infos);
+        }
+
+        content_infos
+    }
+
+    fn parse_tags(sys_tags: Vec<NovelInfoSysTag>) -> Option<Vec<Tag>> {
+        let mut result = vec![];
+        for tag in sys_tags {
+            result.push(Tag {
+                id: Some(tag.sys_tag_id),
+                name: tag.tag_name.trim().to_string(),
+            });
         }
 
         if result.is_empty() {
@@ -464,6 +1080,79 @@ impl SfacgClient {
         }
     }
 
+    /// Convert escaped HTML and leftover escape sequences in the content to plain text
+    fn clean_content(content: String) -> String {
+        let content = crate::unescape_html(content.replace("\\r\\n", "\n").replace("\\n", "\n"));
+
+        let mut result = String::with_capacity(content.len());
+        let mut rest = content.as_str();
+
+        while let Some(begin) = rest.find('<') {
+            result.push_str(&rest[..begin]);
+            rest = &rest[begin..];
+
+            match rest.find('>') {
+                Some(end) if SfacgClient::is_html_tag(&rest[1..end]) => {
+                    let tag = rest[1..end].trim().to_ascii_lowercase();
+                    if tag.starts_with("br") || tag == "/p" {
+                        result.push('\n');
+                    }
+                    rest = &rest[end + 1..];
+                }
+                _ => {
+                    result.push('<');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        result.push_str(rest);
+
+        result
+    }
+
+    fn is_html_tag(tag: &str) -> bool {
+        const TAGS: [&str; 10] = [
+            "p", "br", "div", "span", "b", "i", "u", "em", "strong", "font",
+        ];
+
+        let name = tag.trim().trim_start_matches('/').trim_end_matches('/');
+        let name = name.split_whitespace().next().unwrap_or_default();
+
+        TAGS.iter().any(|item| name.eq_ignore_ascii_case(item))
+    }
+
+    /// Parse a line of content, an image may be surrounded by text on the same line
+    fn parse_line(line: &str, content_infos: &mut ContentInfos) {
+        let mut rest = line;
+
+        while let Some(begin) = rest.find("[img") {
+            SfacgClient::push_text(&rest[..begin], content_infos);
+
+            match rest[begin..].find("[/img]") {
+                Some(end) => {
+                    let end = begin + end + "[/img]".len();
+                    if let Some(url) = SfacgClient::parse_image_url(&rest[begin..end]) {
+                        content_infos.push(ContentInfo::Image(url));
+                    }
+                    rest = &rest[end..];
+                }
+                None => {
+                    error!("Image URL format is incorrect: {line}");
+                    return;
+                }
+            }
+        }
+
+        SfacgClient::push_text(rest, content_infos);
+    }
+
+    fn push_text(text: &str, content_infos: &mut ContentInfos) {
+        let text = text.trim();
+        if !text.is_empty() {
+            content_infos.push(ContentInfo::Text(text.to_string()));
+        }
+    }
+
     fn parse_image_url(line: &str) -> Option<Url> {
         let begin = line.find("http");
         let end = line.find("[/img]");
@@ -473,18 +1162,9 @@ impl SfacgClient {
             return None;
         }
 
-        let begin = begin.unwrap();
-        let end = end.unwrap();
+        let url = line[begin.unwrap()..end.unwrap()].trim();
 
-        let url = line
-            .chars()
-            .skip(begin)
-            .take(end - begin)
-            .collect::<String>()
-            .trim()
-            .to_string();
-
-        match Url::parse(&url) {
+        match Url::parse(url) {
             Ok(url) => Some(url),
             Err(error) => {
                 error!("Image URL parse failed: {error}, content: {line}");
@@ -493,6 +1173,38 @@ impl SfacgClient {
         }
     }
 
+    fn check_tag_count(option: &Options) -> Result<(), Error> {
+        let tag_count = option.tags.as_ref().map_or(0, |tags| tags.len());
+        if tag_count > SfacgClient::MAX_TAGS {
+            return Err(Error::NovelApi(format!(
+                "Too many included tags, max {}",
+                SfacgClient::MAX_TAGS
+            )));
+        }
+
+        let excluded_tag_count = option.excluded_tags.as_ref().map_or(0, |tags| tags.len());
+        if excluded_tag_count > SfacgClient::MAX_EXCLUDED_TAGS {
+            return Err(Error::NovelApi(format!(
+                "Too many excluded tags, max {}",
+                SfacgClient::MAX_EXCLUDED_TAGS
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn check_sort(sort: &str) -> Result<&'static str, Error> {
+        SfacgClient::SORTS
+            .into_iter()
+            .find(|token| *token == sort)
+            .ok_or_else(|| {
+                Error::NovelApi(format!(
+                    "The sort `{sort}` is not supported, the supported sorts are: {}",
+                    SfacgClient::SORTS.join(", ")
+                ))
+            })
+    }
+
     fn bool_to_str(flag: &Option<bool>) -> &'static str {
         if flag.is_some() {
             if *flag.as_ref().unwrap() {
@@ -514,3 +1226,1208 @@ impl SfacgClient {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Cursor;
+
+    use futures_util::TryStreamExt;
+    use http::header::{DATE, RETRY_AFTER, SERVER};
+    use http::{HeaderMap, HeaderValue, StatusCode};
+    use image::{ImageOutputFormat, RgbImage};
+    use pretty_assertions::assert_eq;
+
+    use crate::{ChapterVersion, MockTransport, NovelReader};
+
+    /// The body of the Cloudflare country block page (error 1009), trimmed to its text
+    const CLOUDFLARE_1009: &str = r#"<!DOCTYPE html>
+<html lang="en-US">
+<head><title>Access denied | api.sfacg.com used Cloudflare to restrict access</title></head>
+<body>
+<div id="cf-wrapper">
+  <div id="cf-error-details" class="cf-error-details-wrapper">
+    <div class="cf-wrapper cf-header cf-error-overview">
+      <h1><span class="cf-error-type">Error</span> <span class="cf-error-code">1009</span></h1>
+      <h2 class="cf-subheadline">Access denied</h2>
+    </div>
+    <section class="cf-section cf-wrapper">
+      <h2>What happened?</h2>
+      <p>The owner of this website (api.sfacg.com) has banned the country or region your IP address is in (US) from accessing this website.</p>
+    </section>
+    <div class="cf-error-footer cf-wrapper">
+      <p><span class="cf-footer-item">Cloudflare Ray ID: <strong>7d2a4f3b9c1e0a12</strong></span></p>
+    </div>
+  </div>
+</div>
+</body>
+</html>"#;
+
+    /// A client that answers from `transport` and caches in a temporary database
+    async fn mock_client(transport: MockTransport) -> Result<SfacgClient, Error> {
+        let mut client = SfacgClient::new().await?;
+        client.transport = Some(Arc::new(transport));
+        client.db = OnceCell::from(NovelDB::temp().await?);
+
+        Ok(client)
+    }
+
+    #[test]
+    fn check_tag_count() -> Result<(), Error> {
+        let tag = |id| Tag {
+            id: Some(id),
+            name: id.to_string(),
+        };
+
+        let mut option = Options {
+            tags: Some((0..SfacgClient::MAX_TAGS as u16).map(tag).collect()),
+            ..Default::default()
+        };
+        assert!(SfacgClient::check_tag_count(&option).is_ok());
+
+        option.excluded_tags = Some(
+            (0..=SfacgClient::MAX_EXCLUDED_TAGS as u16)
+                .map(tag)
+                .collect(),
+        );
+        assert!(matches!(
+            SfacgClient::check_tag_count(&option),
+            Err(Error::NovelApi(msg)) if msg.contains("excluded")
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn check_sort() -> Result<(), Error> {
+        assert_eq!(SfacgClient::check_sort("latest")?, "latest");
+        assert!(matches!(
+            SfacgClient::check_sort("newest"),
+            Err(Error::NovelApi(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn clean_content() -> Result<(), Error> {
+        let content =
+            String::from("&lt;p&gt;第一行&lt;/p&gt;第二行<br/>第三行\\r\\n<3 &amp; <魔法>");
+
+        assert_eq!(
+            SfacgClient::clean_content(content),
+            "第一行\n第二行\n第三行\n<3 & <魔法>"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_line() -> Result<(), Error> {
+        let mut content_infos = ContentInfos::new();
+        SfacgClient::parse_line(
+            "插图：[img=1,2]https://rss.sfacg.com/web/novel/images/1.jpg[/img]之后的文字",
+            &mut content_infos,
+        );
+
+        assert!(matches!(
+            content_infos.as_slice(),
+            [ContentInfo::Text(before), ContentInfo::Image(url), ContentInfo::Text(after)]
+                if before == "插图："
+                    && url.as_str() == "https://rss.sfacg.com/web/novel/images/1.jpg"
+                    && after == "之后的文字"
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn novel_info() -> Result<(), Error> {
+        let transport = MockTransport::default()
+            .response(
+                "/novels/1",
+                StatusCode::OK,
+                r#"{
+                    "status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null},
+                    "data": {
+                        "novelName": " 测试小说 ",
+                        "novelCover": "https://rs.sfacg.com/web/novel/images/NovelCover/Big/test.jpg",
+                        "authorName": "作者",
+                        "charCount": 0,
+                        "typeId": 21,
+                        "isFinish": true,
+                        "addTime": "2020-07-08T15:25:15",
+                        "lastUpdateTime": "2020-07-08T15:25:17",
+                        "viewTimes": 100,
+                        "point": 8.5,
+                        "expand": {
+                            "typeName": "魔幻",
+                            "intro": " 第一行 \r\n\r\n 第二行 ",
+                            "sysTags": [{"sysTagId": 74, "tagName": "百合"}],
+                            "fav": 10,
+                            "chapterCount": 320
+                        }
+                    }
+                }"#,
+            )
+            .response(
+                "/novels/2",
+                StatusCode::NOT_FOUND,
+                r#"{"status": {"httpCode": 404, "errorCode": 404, "msgType": 0, "msg": "小说不存在"}}"#,
+            )
+            .response(
+                "/novels/3",
+                StatusCode::INTERNAL_SERVER_ERROR,
+                r#"{"status": {"httpCode": 500, "errorCode": 500, "msgType": 0, "msg": "服务器错误"}}"#,
+            );
+        let client = mock_client(transport).await?;
+
+        let novel_info = client.novel_info(1).await?.unwrap();
+        assert_eq!(novel_info.name, "测试小说");
+        assert_eq!(novel_info.word_count, None);
+        assert_eq!(
+            novel_info.introduction,
+            Some(vec![String::from("第一行"), String::from("第二行")])
+        );
+        assert_eq!(novel_info.category.unwrap().name, "魔幻");
+        assert_eq!(novel_info.tags.unwrap()[0].name, "百合");
+        assert_eq!(novel_info.view_count, Some(100));
+        assert_eq!(novel_info.favorite_count, Some(10));
+        assert_eq!(novel_info.rating, Some(8.5));
+        assert_eq!(novel_info.chapter_count, Some(320));
+
+        assert!(client.novel_info(2).await?.is_none());
+
+        assert!(matches!(
+            client.novel_info(3).await,
+            Err(Error::Http { code, .. }) if code == StatusCode::INTERNAL_SERVER_ERROR
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn novel_cards() -> Result<(), Error> {
+        let mut cover = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::new(64, 32))
+            .write_to(&mut Cursor::new(&mut cover), ImageOutputFormat::Png)?;
+
+        let novel = |id: u32| {
+            format!(
+                r#"{{
+                    "status": {{"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null}},
+                    "data": {{
+                        "novelName": "测试小说{id}",
+                        "novelCover": "https://rs.sfacg.com/web/novel/images/NovelCover/Big/card{id}.png",
+                        "authorName": "作者",
+                        "charCount": 0,
+                        "typeId": 21,
+                        "isFinish": true,
+                        "addTime": "2020-07-08T15:25:15",
+                        "lastUpdateTime": "2020-07-08T15:25:17",
+                        "expand": {{"typeName": "魔幻", "intro": "简介", "sysTags": []}}
+                    }}
+                }}"#
+            )
+        };
+        let transport = MockTransport::default()
+            .response("/novels/1", StatusCode::OK, novel(1))
+            .response(
+                "/web/novel/images/NovelCover/Big/card1.png",
+                StatusCode::OK,
+                cover,
+            )
+            .response(
+                "/novels/2",
+                StatusCode::NOT_FOUND,
+                r#"{"status": {"httpCode": 404, "errorCode": 404, "msgType": 0, "msg": "小说不存在"}}"#,
+            )
+            .response("/novels/3", StatusCode::OK, novel(3));
+        let client = mock_client(transport).await?;
+
+        let cards = client.novel_cards(&[3, 2, 1], 2).await?;
+        assert_eq!(cards.len(), 2);
+        assert_eq!(cards[0].info.name, "测试小说3");
+        assert!(cards[0].cover.is_none());
+        assert_eq!(cards[1].info.name, "测试小说1");
+        assert_eq!(cards[1].cover.as_ref().map(|cover| cover.width()), Some(64));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn save_cover() -> Result<(), Error> {
+        let mut cover = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::new(64, 32))
+            .write_to(&mut Cursor::new(&mut cover), ImageOutputFormat::Png)?;
+
+        let transport = MockTransport::default().response(
+            "/web/novel/images/NovelCover/Big/save_cover.png",
+            StatusCode::OK,
+            cover.clone(),
+        );
+        let client = mock_client(transport).await?;
+
+        let mut novel_info = NovelInfo {
+            name: String::from("测试小说"),
+            ..Default::default()
+        };
+        let path = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        assert!(client.save_cover(&novel_info, &path).await.is_err());
+
+        novel_info.cover_url = Some(Url::parse(
+            "https://rs.sfacg.com/web/novel/images/NovelCover/Big/save_cover.png",
+        )?);
+        client.save_cover(&novel_info, &path).await?;
+
+        let path = path.with_extension("png");
+        assert_eq!(std::fs::read(&path)?, cover);
+        std::fs::remove_file(path)?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn image_cache_fs() -> Result<(), Error> {
+        let mut image = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::new(8, 8))
+            .write_to(&mut Cursor::new(&mut image), ImageOutputFormat::Png)?;
+
+        let transport = MockTransport::default().response(
+            "/web/novel/images/NovelCover/Big/fs.png",
+            StatusCode::OK,
+            image.clone(),
+        );
+        let mut client = mock_client(transport).await?;
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        client.image_cache_fs(dir.clone());
+
+        let url = Url::parse("https://rs.sfacg.com/web/novel/images/NovelCover/Big/fs.png")?;
+        assert_eq!(client.image_bytes(&url).await?, image);
+        assert_eq!(crate::find_image_file(&dir, &url).await?, Some(image));
+
+        tokio::fs::remove_dir_all(dir).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn no_cache() -> Result<(), Error> {
+        let mut image = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::new(8, 8))
+            .write_to(&mut Cursor::new(&mut image), ImageOutputFormat::Png)?;
+
+        let transport = MockTransport::default()
+            .response(
+                "/Chaps/176501",
+                StatusCode::OK,
+                r#"{
+                    "status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null},
+                    "data": {"expand": {"content": "第一行"}}
+                }"#,
+            )
+            .response(
+                "/web/novel/images/NovelCover/Big/no_cache.png",
+                StatusCode::OK,
+                image.clone(),
+            );
+        let mut client = mock_client(transport).await?;
+        client.no_cache();
+
+        let info = ChapterInfo {
+            identifier: Identifier::Id(176501),
+            title: String::from("第一章"),
+            is_vip: None,
+            is_accessible: None,
+            is_valid: None,
+            word_count: None,
+            update_time: None,
+        };
+        assert_eq!(client.content_infos(&info).await?.len(), 1);
+
+        let url = Url::parse("https://rs.sfacg.com/web/novel/images/NovelCover/Big/no_cache.png")?;
+        assert_eq!(client.image_bytes(&url).await?, image);
+
+        assert!(!client.is_content_cached(&info).await?);
+        assert!(client.db().await?.find_image_bytes(&url).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn region_blocked() -> Result<(), Error> {
+        let transport = MockTransport::default()
+            .response(
+                "/novels/1",
+                StatusCode::UNAVAILABLE_FOR_LEGAL_REASONS,
+                "<html><body>Unavailable For Legal Reasons</body></html>",
+            )
+            .response("/novels/2", StatusCode::FORBIDDEN, CLOUDFLARE_1009)
+            .response(
+                "/web/novel/images/NovelCover/Big/region_blocked.jpg",
+                StatusCode::FORBIDDEN,
+                CLOUDFLARE_1009,
+            )
+            .response(
+                "/novels/3",
+                StatusCode::FORBIDDEN,
+                "<html><head><title>403 Forbidden</title></head><body><center><h1>403 Forbidden</h1></center><hr><center>nginx</center></body></html>",
+            )
+            .response_with_headers(
+                "/novels/4",
+                StatusCode::SERVICE_UNAVAILABLE,
+                HeaderMap::from_iter([
+                    (RETRY_AFTER, HeaderValue::from_static("120")),
+                    (SERVER, HeaderValue::from_static("cloudflare")),
+                ]),
+                "<html><body>The service in your region is under maintenance</body></html>",
+            );
+        let client = mock_client(transport).await?;
+
+        assert!(matches!(
+            client.novel_info(1).await,
+            Err(Error::RegionBlocked(_))
+        ));
+        assert!(matches!(
+            client.novel_info(2).await,
+            Err(Error::RegionBlocked(_))
+        ));
+        assert!(matches!(
+            client
+                .image_bytes(&Url::parse(
+                    "https://rs.sfacg.com/web/novel/images/NovelCover/Big/region_blocked.jpg"
+                )?)
+                .await,
+            Err(Error::RegionBlocked(_))
+        ));
+        assert!(matches!(
+            client.novel_info(3).await,
+            Err(Error::Http { code, .. }) if code == StatusCode::FORBIDDEN
+        ));
+
+        let error = client.novel_info(4).await.unwrap_err();
+        assert!(matches!(
+            error,
+            Error::Http { code, .. } if code == StatusCode::SERVICE_UNAVAILABLE
+        ));
+        assert_eq!(error.retry_after(), Some(Duration::from_secs(120)));
+        assert_eq!(
+            error.headers().and_then(|headers| headers.get(SERVER)),
+            Some(&HeaderValue::from_static("cloudflare"))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn time_offset() -> Result<(), Error> {
+        let transport = MockTransport::default().response_with_headers(
+            "/novels/1",
+            StatusCode::NOT_FOUND,
+            HeaderMap::from_iter([(
+                DATE,
+                HeaderValue::from_static("Fri, 01 Jan 2100 00:00:00 GMT"),
+            )]),
+            "",
+        );
+        let client = mock_client(transport).await?;
+
+        assert_eq!(client.time_offset(), 0);
+        assert!(client.novel_info(1).await.is_err());
+        assert!(client.time_offset() > 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rate_limited() -> Result<(), Error> {
+        let transport = MockTransport::default().response(
+            "/novels/1",
+            StatusCode::TOO_MANY_REQUESTS,
+            "<html><body>Too Many Requests</body></html>",
+        );
+        let client = mock_client(transport).await?;
+
+        let error = client.novel_info(1).await.unwrap_err();
+        assert!(matches!(
+            error,
+            Error::Http { code, .. } if code == StatusCode::TOO_MANY_REQUESTS
+        ));
+        assert!(error.is_retryable());
+        assert!(error.headers().is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn update_reading_progress() -> Result<(), Error> {
+        let transport = MockTransport::default().response(
+            "/user/novels/1/readprogress",
+            StatusCode::UNAUTHORIZED,
+            r#"{"status": {"httpCode": 401, "errorCode": 502, "msgType": 0, "msg": "需要登录"}}"#,
+        );
+        let client = mock_client(transport).await?;
+
+        assert!(matches!(
+            client.update_reading_progress(1, &Identifier::Id(2)).await,
+            Err(Error::NotLoggedIn)
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn novels_page() -> Result<(), Error> {
+        let transport = MockTransport::default().response(
+            "/novels/0/sysTags/novels",
+            StatusCode::OK,
+            r#"{
+                "status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null},
+                "data": [{"novelId": 1}, {"novelId": 2}],
+                "total": 42
+            }"#,
+        );
+        let client = mock_client(transport).await?;
+
+        let page = client.novels_page(&Options::default(), 0, 2).await?;
+        assert_eq!(
+            page,
+            NovelsPage {
+                ids: vec![1, 2],
+                total: Some(42)
+            }
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn init_session() -> Result<(), Error> {
+        let transport = MockTransport::default().response(
+            "/position",
+            StatusCode::OK,
+            r#"{"status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null}}"#,
+        );
+        let client = mock_client(transport).await?;
+        assert!(client.init_session().await.is_ok());
+
+        let client = mock_client(MockTransport::default()).await?;
+        assert!(client.init_session().await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn get_raw() -> Result<(), Error> {
+        let body = r#"{"status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null}, "data": {"unknown": 1}}"#;
+        let transport = MockTransport::default().response("/position", StatusCode::OK, body);
+        let client = mock_client(transport).await?;
+        assert_eq!(client.get_raw("/position").await?, body);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn chapters_stream() -> Result<(), Error> {
+        let transport = MockTransport::default().response(
+            "/novels/1/dirs",
+            StatusCode::OK,
+            r#"{
+                "status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null},
+                "data": {
+                    "volumeList": [
+                        {"title": "第一卷", "chapterList": [{"chapId": 1, "title": "第一章"}]},
+                        {"title": "第二卷", "chapterList": [{"chapId": 2, "title": "第二章"}]}
+                    ]
+                }
+            }"#,
+        );
+        let client = mock_client(transport).await?;
+
+        let chapter_infos = client.chapters_stream(1).try_collect::<Vec<_>>().await?;
+        assert_eq!(
+            chapter_infos
+                .iter()
+                .map(|chapter_info| chapter_info.title.as_str())
+                .collect::<Vec<_>>(),
+            vec!["第一章", "第二章"]
+        );
+
+        assert!(client
+            .chapters_stream(2)
+            .try_collect::<Vec<_>>()
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_all() -> Result<(), Error> {
+        // The mock ignores the page, so every page repeats the first one
+        let novels = (1..=20)
+            .map(|id| format!(r#"{{"novelId": {id}}}"#))
+            .collect::<Vec<_>>()
+            .join(",");
+        let transport = MockTransport::default().response(
+            "/search/novels/result/new",
+            StatusCode::OK,
+            format!(
+                r#"{{
+                    "status": {{"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null}},
+                    "data": {{"novels": [{novels}]}}
+                }}"#
+            ),
+        );
+        let client = mock_client(transport).await?;
+
+        assert_eq!(client.search_all("测试", 5).await?, vec![1, 2, 3, 4, 5]);
+        assert_eq!(
+            client.search_all("测试", 50).await?,
+            (1..=20).collect::<Vec<_>>()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_novels() -> Result<(), Error> {
+        let transport = MockTransport::default().response(
+            "/search/novels/result/new",
+            StatusCode::OK,
+            r#"{
+                "status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null},
+                "data": {"novels": [{
+                    "novelId": 1,
+                    "novelName": " 测试小说 ",
+                    "novelCover": "https://rs.sfacg.com/web/novel/images/NovelCover/Big/test.jpg",
+                    "authorName": "作者",
+                    "charCount": 1000,
+                    "typeId": 21,
+                    "isFinish": false,
+                    "addTime": "2020-07-08T15:25:15",
+                    "lastUpdateTime": "2020-07-08T15:25:17",
+                    "expand": {
+                        "typeName": "魔幻",
+                        "intro": "简介",
+                        "sysTags": [{"sysTagId": 74, "tagName": "百合"}]
+                    }
+                }]}
+            }"#,
+        );
+        let client = mock_client(transport).await?;
+
+        let novel_infos = client.search_novels("测试", 0, 12, "hot").await?;
+        assert_eq!(novel_infos.len(), 1);
+        assert_eq!(novel_infos[0].id, 1);
+        assert_eq!(novel_infos[0].name, "测试小说");
+        assert_eq!(novel_infos[0].word_count, Some(1000));
+        assert_eq!(novel_infos[0].category.as_ref().unwrap().name, "魔幻");
+
+        assert!(client
+            .search_novels("测试", 0, 12, "unknown")
+            .await
+            .is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn search_infos_in() -> Result<(), Error> {
+        let novel = |type_id: u16, type_name: &str| {
+            format!(
+                r#"{{
+                    "status": {{"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null}},
+                    "data": {{
+                        "novelName": "测试小说",
+                        "novelCover": "https://rs.sfacg.com/web/novel/images/NovelCover/Big/test.jpg",
+                        "authorName": "作者",
+                        "charCount": 0,
+                        "typeId": {type_id},
+                        "isFinish": true,
+                        "addTime": "2020-07-08T15:25:15",
+                        "lastUpdateTime": "2020-07-08T15:25:17",
+                        "expand": {{"typeName": "{type_name}", "intro": "简介", "sysTags": []}}
+                    }}
+                }}"#
+            )
+        };
+        let transport = MockTransport::default()
+            .response(
+                "/search/novels/result/new",
+                StatusCode::OK,
+                r#"{
+                    "status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null},
+                    "data": {"novels": [{"novelId": 1}, {"novelId": 2}, {"novelId": 3}]}
+                }"#,
+            )
+            .response("/novels/1", StatusCode::OK, novel(21, "魔幻"))
+            .response("/novels/2", StatusCode::OK, novel(22, "玄幻"))
+            .response("/novels/3", StatusCode::OK, novel(21, "魔幻"));
+        let client = mock_client(transport).await?;
+
+        let category = Category {
+            id: Some(21),
+            name: String::from("魔幻"),
+        };
+        assert_eq!(
+            client.search_infos_in("测试", &category, 0, 12, 2).await?,
+            vec![1, 3]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn volume_infos() -> Result<(), Error> {
+        let transport = MockTransport::default().response(
+            "/novels/1/dirs",
+            StatusCode::OK,
+            r#"{
+                "status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null},
+                "data": {
+                    "volumeList": [{
+                        "title": "第一卷",
+                        "chapterList": [
+                            {"chapId": 1, "title": "第一章", "charCount": 3000},
+                            {"chapId": 2, "title": "第二章", "charCount": -1}
+                        ]
+                    }]
+                }
+            }"#,
+        );
+        let client = mock_client(transport).await?;
+
+        let volume_infos = client.volume_infos(1).await?;
+        let chapter_infos = &volume_infos[0].chapter_infos;
+        assert_eq!(chapter_infos[0].word_count, Some(3000));
+        assert_eq!(chapter_infos[1].word_count, None);
+
+        assert_eq!(
+            client.toc(1).await?,
+            vec![(
+                String::from("第一卷"),
+                vec![
+                    (Identifier::Id(1), String::from("第一章")),
+                    (Identifier::Id(2), String::from("第二章"))
+                ]
+            )]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn content_infos_range() -> Result<(), Error> {
+        let transport = MockTransport::default()
+            .response(
+                "/novels/1/dirs",
+                StatusCode::OK,
+                r#"{
+                    "status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null},
+                    "data": {
+                        "volumeList": [
+                            {"title": "第一卷", "chapterList": [{"chapId": 176401, "title": "第一章"}]},
+                            {"title": "第二卷", "chapterList": [
+                                {"chapId": 176402, "title": "第二章"},
+                                {"chapId": 176403, "title": "第三章"}
+                            ]}
+                        ]
+                    }
+                }"#,
+            )
+            .response(
+                "/Chaps/176402",
+                StatusCode::OK,
+                r#"{
+                    "status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null},
+                    "data": {"expand": {"content": "第二章的内容"}}
+                }"#,
+            )
+            .response(
+                "/Chaps/176403",
+                StatusCode::OK,
+                r#"{
+                    "status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null},
+                    "data": {"expand": {"content": "第三章的内容"}}
+                }"#,
+            );
+        let client = mock_client(transport).await?;
+
+        let chapters = client.content_infos_range(1, 1..10, 2).await?;
+        assert_eq!(
+            chapters
+                .iter()
+                .map(|(info, _)| info.title.as_str())
+                .collect::<Vec<_>>(),
+            vec!["第二章", "第三章"]
+        );
+        assert!(matches!(&chapters[1].1[0], ContentInfo::Text(text) if text == "第三章的内容"));
+
+        assert!(client.content_infos_range(1, 5..10, 2).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn novel_reader() -> Result<(), Error> {
+        let transport = MockTransport::default()
+            .response(
+                "/novels/1/dirs",
+                StatusCode::OK,
+                r#"{
+                    "status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null},
+                    "data": {
+                        "volumeList": [
+                            {"title": "第一卷", "chapterList": [{"chapId": 1, "title": "第一章"}]},
+                            {"title": "第二卷", "chapterList": [
+                                {"chapId": 2, "title": "第二章"},
+                                {"chapId": 3, "title": "第三章"}
+                            ]}
+                        ]
+                    }
+                }"#,
+            )
+            .response(
+                "/Chaps/1",
+                StatusCode::OK,
+                r#"{
+                    "status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null},
+                    "data": {"expand": {"content": "第一章的内容"}}
+                }"#,
+            )
+            .response(
+                "/Chaps/2",
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "<html><body>Internal Server Error</body></html>",
+            )
+            .response(
+                "/Chaps/3",
+                StatusCode::OK,
+                r#"{
+                    "status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null},
+                    "data": {"expand": {"content": "第三章的内容"}}
+                }"#,
+            );
+        let client = mock_client(transport).await?.into_shared();
+
+        let mut reader = NovelReader::new(Arc::clone(&client), 1).await?;
+        assert_eq!(reader.len(), 3);
+        assert_eq!(reader.chapter_infos()[1].title, "第二章");
+        assert!(matches!(reader.chapter(3).await, Err(Error::NovelApi(_))));
+
+        reader.prefetch(true);
+        let content_infos = reader.chapter(0).await?;
+        assert!(matches!(&content_infos[0], ContentInfo::Text(text) if text == "第一章的内容"));
+        assert!(matches!(
+            reader.chapter(1).await,
+            Err(Error::Http { code, .. }) if code == StatusCode::INTERNAL_SERVER_ERROR
+        ));
+
+        // The prefetch of the third chapter runs in the background even if another chapter is read
+        reader.chapter(0).await?;
+        let third = &reader.chapter_infos()[2];
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !client.is_content_cached(third).await? {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            Ok::<_, Error>(())
+        })
+        .await
+        .map_err(|error| Error::NovelApi(error.to_string()))??;
+
+        let content_infos = reader.chapter(2).await?;
+        assert!(matches!(&content_infos[0], ContentInfo::Text(text) if text == "第三章的内容"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn export_txt() -> Result<(), Error> {
+        let transport = MockTransport::default()
+            .response(
+                "/novels/1/dirs",
+                StatusCode::OK,
+                r#"{
+                    "status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null},
+                    "data": {
+                        "volumeList": [{
+                            "title": "第一卷",
+                            "chapterList": [
+                                {"chapId": 176001, "title": "第一章", "needFireMoney": 0},
+                                {"chapId": 176002, "title": "第二章", "needFireMoney": 10}
+                            ]
+                        }]
+                    }
+                }"#,
+            )
+            .response(
+                "/Chaps/176001",
+                StatusCode::OK,
+                r#"{
+                    "status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null},
+                    "data": {"expand": {"content": "第一行\n第二行"}}
+                }"#,
+            );
+        let client = mock_client(transport).await?;
+
+        let mut out = Vec::new();
+        client.export_txt(1, &mut out, 2).await?;
+        assert_eq!(
+            simdutf8::basic::from_utf8(&out)?,
+            "第一卷\n\n第一章\n\n第一行\n第二行\n\n第二章\n\n[This chapter is locked]\n\n"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn chapter_preview() -> Result<(), Error> {
+        let content = |content: &str| {
+            format!(
+                r#"{{
+                    "status": {{"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null}},
+                    "data": {{"expand": {{"content": "{content}"}}}}
+                }}"#
+            )
+        };
+        let transport = MockTransport::default()
+            .response("/Chaps/176101", StatusCode::OK, content("第一行\\n第二行"))
+            .response(
+                "/Chaps/176102",
+                StatusCode::OK,
+                content("第一行\\n本章为VIP章节，订阅本章后可继续阅读"),
+            )
+            .response("/Chaps/176103", StatusCode::OK, content(""));
+        let client = mock_client(transport).await?;
+        let info = |id, is_accessible| ChapterInfo {
+            identifier: Identifier::Id(id),
+            title: id.to_string(),
+            is_vip: Some(true),
+            is_accessible: Some(is_accessible),
+            is_valid: None,
+            word_count: None,
+            update_time: None,
+        };
+
+        let preview = client.chapter_preview(&info(176101, true)).await?.unwrap();
+        assert!(!preview.is_preview);
+        assert_eq!(preview.content_infos.len(), 2);
+
+        let preview = client.chapter_preview(&info(176102, false)).await?.unwrap();
+        assert!(preview.is_preview);
+        assert!(matches!(&preview.content_infos[0], ContentInfo::Text(text) if text == "第一行"));
+
+        // The chapter looks accessible, but the site only returns the preview
+        let preview = client.chapter_preview(&info(176102, true)).await?.unwrap();
+        assert!(preview.is_preview);
+        assert!(!client.is_content_cached(&info(176102, true)).await?);
+
+        assert!(client
+            .chapter_preview(&info(176103, false))
+            .await?
+            .is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn multi_page_content() -> Result<(), Error> {
+        let transport = MockTransport::default()
+            .response(
+                "/Chaps/176201",
+                StatusCode::OK,
+                r#"{
+                    "status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null},
+                    "data": {"expand": {"content": "第一行\n第二行", "hasNextPage": true}}
+                }"#,
+            )
+            .response(
+                "/Chaps/176201?expand=content&page=1",
+                StatusCode::OK,
+                r#"{
+                    "status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null},
+                    "data": {"expand": {"content": "第三行", "hasNextPage": false}}
+                }"#,
+            );
+        let client = mock_client(transport).await?;
+        let info = ChapterInfo {
+            identifier: Identifier::Id(176201),
+            title: String::from("第一章"),
+            is_vip: None,
+            is_accessible: None,
+            is_valid: None,
+            word_count: None,
+            update_time: None,
+        };
+
+        assert_eq!(client.fetch_content(&info).await?, "第一行\n第二行\n第三行");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn verify_cached_chapter() -> Result<(), Error> {
+        let client = mock_client(MockTransport::default()).await?;
+        let info = ChapterInfo {
+            identifier: Identifier::Id(176301),
+            title: String::from("第一章"),
+            is_vip: None,
+            is_accessible: None,
+            is_valid: None,
+            word_count: Some(3000),
+            update_time: None,
+        };
+
+        assert_eq!(
+            client.verify_cached_chapter(&info).await?,
+            CacheVerdict::NotCached
+        );
+
+        client.cache_content(&info, &"正文".repeat(1500)).await?;
+        assert_eq!(
+            client.verify_cached_chapter(&info).await?,
+            CacheVerdict::Match
+        );
+
+        client.cache_content(&info, &"正文".repeat(100)).await?;
+        assert_eq!(
+            client.verify_cached_chapter(&info).await?,
+            CacheVerdict::Mismatch {
+                cached: 200,
+                expected: 3000
+            }
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn categories() -> Result<(), Error> {
+        let category = |name: &str| {
+            format!(
+                r#"{{
+                    "status": {{"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null}},
+                    "data": [{{"typeId": 21, "typeName": "{name}"}}]
+                }}"#
+            )
+        };
+        let client = mock_client(MockTransport::default().response(
+            "/noveltypes",
+            StatusCode::OK,
+            category("魔幻"),
+        ))
+        .await?;
+        let other_client = mock_client(MockTransport::default().response(
+            "/noveltypes",
+            StatusCode::OK,
+            category("玄幻"),
+        ))
+        .await?;
+
+        assert_eq!(client.categories().await?[0].name, "魔幻");
+        assert_eq!(other_client.categories().await?[0].name, "玄幻");
+
+        // A failed request does not initialize the cache, so it is retried by the next call
+        let failed_client = mock_client(MockTransport::default()).await?;
+        assert!(failed_client.categories().await.is_err());
+        assert!(!failed_client.categories.initialized());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn chapter_versions() -> Result<(), Error> {
+        let client = mock_client(MockTransport::default()).await?;
+        let info = ChapterInfo {
+            identifier: Identifier::Id(1),
+            title: String::from("第一章"),
+            is_vip: None,
+            is_accessible: None,
+            is_valid: None,
+            word_count: None,
+            update_time: None,
+        };
+
+        let versions = client.chapter_versions(&info).await?;
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions[0].id, ChapterVersion::CURRENT);
+
+        assert!(matches!(
+            client.content_infos_version(&info, 1).await,
+            Err(Error::NovelApi(_))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn tags_for_category() -> Result<(), Error> {
+        let transport = MockTransport::default().response(
+            "/novels/21/sysTags",
+            StatusCode::OK,
+            r#"{
+                "status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null},
+                "data": [{"sysTagId": 1, "tagName": " 魔法 "}]
+            }"#,
+        );
+        let client = mock_client(transport).await?;
+
+        let category = Category {
+            id: Some(21),
+            name: String::from("魔幻"),
+        };
+        let tags = client.tags_for_category(&category).await?;
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags[0].name, "魔法");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn browse_taxonomy() -> Result<(), Error> {
+        let transport = MockTransport::default()
+            .response(
+                "/noveltypes",
+                StatusCode::OK,
+                r#"{
+                    "status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null},
+                    "data": [{"typeId": 21, "typeName": "魔幻"}, {"typeId": 22, "typeName": "玄幻"}]
+                }"#,
+            )
+            .response(
+                "/novels/21/sysTags",
+                StatusCode::OK,
+                r#"{
+                    "status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null},
+                    "data": [{"sysTagId": 1, "tagName": "魔法"}]
+                }"#,
+            )
+            .response(
+                "/novels/22/sysTags",
+                StatusCode::OK,
+                r#"{
+                    "status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null},
+                    "data": [{"sysTagId": 2, "tagName": "修真"}, {"sysTagId": 3, "tagName": "热血"}]
+                }"#,
+            );
+        let client = mock_client(transport).await?;
+
+        let taxonomy = client.browse_taxonomy().await?;
+        assert_eq!(
+            taxonomy
+                .iter()
+                .map(|(category, tags)| (category.name.as_str(), tags.len()))
+                .collect::<Vec<_>>(),
+            vec![("魔幻", 1), ("玄幻", 2)]
+        );
+        assert!(client.taxonomy.initialized());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn author_info() -> Result<(), Error> {
+        let transport = MockTransport::default().response(
+            "/users/1",
+            StatusCode::OK,
+            r#"{
+                "status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null},
+                "data": {
+                    "nickName": " 作者 ",
+                    "avatar": "https://rs.sfacg.com/web/account/images/avatars/app/1.jpg",
+                    "expand": {"introduction": "", "novelCount": 3}
+                }
+            }"#,
+        );
+        let client = mock_client(transport).await?;
+
+        let author_info = client.author_info(1).await?;
+        assert_eq!(author_info.name, "作者");
+        assert!(author_info.avatar_url.is_some());
+        assert_eq!(author_info.introduction, None);
+        assert_eq!(author_info.novel_count, Some(3));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn no_proxy() -> Result<(), Error> {
+        let mut client = SfacgClient::new().await?;
+
+        client.proxy(Url::parse("http://127.0.0.1:8080")?);
+        client.no_proxy();
+        assert!(client.proxy.is_none());
+        assert!(client.no_proxy);
+
+        client.proxy(Url::parse("http://127.0.0.1:8080")?);
+        assert!(client.proxy.is_some());
+        assert!(!client.no_proxy);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn pin_cert() -> Result<(), Error> {
+        let mut client = SfacgClient::new().await?;
+        client.cert("cert.pem");
+        client.pin_cert(&[0x30, 0x00]);
+
+        assert!(matches!(client.client().await, Err(Error::NovelApi(_))));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reset_connections() -> Result<(), Error> {
+        let transport = MockTransport::default().response(
+            "/position",
+            StatusCode::OK,
+            r#"{"status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null}}"#,
+        );
+        let client = mock_client(transport).await?;
+
+        client.init_session().await?;
+        client.reset_connections().await?;
+        client.init_session().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn reqwest_client() -> Result<(), Error> {
+        let transport = MockTransport::default().response(
+            "/position",
+            StatusCode::OK,
+            r#"{"status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null}}"#,
+        );
+        let mut client = SfacgClient::new().await?;
+        client.reqwest_client(reqwest::Client::new());
+        client.transport = Some(Arc::new(transport));
+
+        client.init_session().await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn into_shared() -> Result<(), Error> {
+        let transport = MockTransport::default().response(
+            "/position",
+            StatusCode::OK,
+            r#"{"status": {"httpCode": 200, "errorCode": 200, "msgType": 0, "msg": null}}"#,
+        );
+        let client = mock_client(transport).await?.into_shared();
+
+        let tasks = (0..2).map(|_| {
+            let client = Arc::clone(&client);
+            tokio::spawn(async move { client.init_session().await })
+        });
+        for task in tasks.collect::<Vec<_>>() {
+            task.await??;
+        }
+
+        Ok(())
+    }
+}
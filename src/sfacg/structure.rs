@@ -1,5 +1,5 @@
 use chrono::NaiveDateTime;
-use http::StatusCode;
+use http::{HeaderMap, StatusCode};
 use serde::{Deserialize, Serialize};
 use url::Url;
 
@@ -30,11 +30,27 @@ impl Status {
         self.http_code == StatusCode::UNAUTHORIZED && self.error_code == 502
     }
 
+    #[must_use]
+    pub(crate) fn invalid_credentials(&self) -> bool {
+        self.http_code == StatusCode::UNAUTHORIZED && !self.unauthorized()
+    }
+
+    pub(crate) fn check_login(self) -> Result<(), Error> {
+        if self.invalid_credentials() {
+            return Err(Error::InvalidCredentials(
+                self.msg.unwrap_or_default().trim().to_string(),
+            ));
+        }
+
+        self.check()
+    }
+
     pub(crate) fn check(self) -> Result<(), Error> {
         if !self.ok() {
             return Err(Error::Http {
                 code: StatusCode::from_u16(self.http_code)?,
                 msg: self.msg.unwrap().trim().to_string(),
+                headers: HeaderMap::new(),
             })?;
         }
 
@@ -76,10 +92,51 @@ pub(crate) struct UserData {
     pub nick_name: String,
 }
 
+#[must_use]
+#[derive(Deserialize)]
+pub(crate) struct SignInfoResponse {
+    pub status: Status,
+    pub data: Option<Vec<SignInfoData>>,
+}
+
+#[must_use]
+#[derive(Deserialize)]
+pub(crate) struct SignInfoData {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+#[must_use]
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SignInRequest {
+    pub sign_date: String,
+}
+
+#[must_use]
+#[derive(Deserialize)]
+pub(crate) struct SignInResponse {
+    pub status: Status,
+}
+
+#[must_use]
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ReadingProgressRequest {
+    pub chap_id: u32,
+}
+
+#[must_use]
+#[derive(Deserialize)]
+pub(crate) struct ReadingProgressResponse {
+    pub status: Status,
+}
+
 #[must_use]
 #[derive(Serialize)]
 pub(crate) struct NovelInfoRequest {
-    pub expand: &'static str,
+    pub expand: String,
 }
 
 #[must_use]
@@ -96,11 +153,17 @@ pub(crate) struct NovelInfoData {
     pub novel_name: String,
     pub novel_cover: Url,
     pub author_name: String,
+    #[serde(default)]
+    pub author_id: Option<u32>,
     pub char_count: i32,
     pub type_id: u16,
     pub is_finish: bool,
     pub add_time: NaiveDateTime,
     pub last_update_time: NaiveDateTime,
+    #[serde(default)]
+    pub view_times: Option<u32>,
+    #[serde(default)]
+    pub point: Option<f32>,
     pub expand: NovelInfoExpand,
 }
 
@@ -111,6 +174,10 @@ pub(crate) struct NovelInfoExpand {
     pub type_name: String,
     pub intro: String,
     pub sys_tags: Vec<NovelInfoSysTag>,
+    #[serde(default)]
+    pub fav: Option<u32>,
+    #[serde(default)]
+    pub chapter_count: Option<u32>,
 }
 
 #[must_use]
@@ -121,6 +188,40 @@ pub(crate) struct NovelInfoSysTag {
     pub tag_name: String,
 }
 
+#[must_use]
+#[derive(Serialize)]
+pub(crate) struct AuthorInfoRequest {
+    pub expand: &'static str,
+}
+
+#[must_use]
+#[derive(Deserialize)]
+pub(crate) struct AuthorInfoResponse {
+    pub status: Status,
+    pub data: Option<AuthorInfoData>,
+}
+
+#[must_use]
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AuthorInfoData {
+    pub nick_name: String,
+    #[serde(default)]
+    pub avatar: String,
+    #[serde(default)]
+    pub expand: AuthorInfoExpand,
+}
+
+#[must_use]
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AuthorInfoExpand {
+    #[serde(default)]
+    pub introduction: String,
+    #[serde(default)]
+    pub novel_count: Option<u32>,
+}
+
 #[must_use]
 #[derive(Deserialize)]
 pub(crate) struct NovelsDirsResponse {
@@ -149,11 +250,16 @@ pub(crate) struct NovelsDirsVolumeInfo {
 pub(crate) struct NovelsDirsChapterInfo {
     pub chap_id: u32,
     pub title: String,
-    pub char_count: i16,
-    pub is_vip: bool,
-    pub need_fire_money: i16,
-    #[serde(rename = "AddTime")]
-    pub add_time: NaiveDateTime,
+    // The fields below are occasionally absent for brand-new chapters
+    #[serde(default)]
+    pub char_count: Option<i16>,
+    #[serde(default)]
+    pub is_vip: Option<bool>,
+    #[serde(default)]
+    pub need_fire_money: Option<i16>,
+    #[serde(rename = "AddTime", default)]
+    pub add_time: Option<NaiveDateTime>,
+    #[serde(default)]
     pub update_time: Option<NaiveDateTime>,
 }
 
@@ -161,6 +267,7 @@ pub(crate) struct NovelsDirsChapterInfo {
 #[derive(Serialize)]
 pub(crate) struct ChapsRequest {
     pub expand: &'static str,
+    pub page: Option<u16>,
 }
 
 #[must_use]
@@ -178,8 +285,32 @@ pub(crate) struct ChapsData {
 
 #[must_use]
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub(crate) struct ChapsExpand {
     pub content: String,
+    #[serde(default)]
+    pub has_next_page: bool,
+}
+
+#[must_use]
+#[derive(Deserialize)]
+pub(crate) struct ChapsAudioResponse {
+    pub status: Status,
+    pub data: Option<ChapsAudioData>,
+}
+
+#[must_use]
+#[derive(Deserialize)]
+pub(crate) struct ChapsAudioData {
+    pub expand: ChapsAudioExpand,
+}
+
+#[must_use]
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ChapsAudioExpand {
+    #[serde(default)]
+    pub audio_url: Option<String>,
 }
 
 #[must_use]
@@ -189,6 +320,7 @@ pub(crate) struct SearchRequest {
     pub q: String,
     pub size: u16,
     pub sort: &'static str,
+    pub expand: Option<&'static str>,
 }
 
 #[must_use]
@@ -211,6 +343,28 @@ pub(crate) struct SearchNovelInfo {
     pub novel_id: u32,
 }
 
+#[must_use]
+#[derive(Deserialize)]
+pub(crate) struct SearchNovelsResponse {
+    pub status: Status,
+    pub data: Option<SearchNovelsData>,
+}
+
+#[must_use]
+#[derive(Deserialize)]
+pub(crate) struct SearchNovelsData {
+    pub novels: Vec<SearchNovelData>,
+}
+
+#[must_use]
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SearchNovelData {
+    pub novel_id: u32,
+    #[serde(flatten)]
+    pub info: NovelInfoData,
+}
+
 #[must_use]
 #[derive(Serialize)]
 pub(crate) struct FavoritesRequest {
@@ -302,6 +456,8 @@ pub(crate) struct NovelsRequest {
 pub(crate) struct NovelsResponse {
     pub status: Status,
     pub data: Option<Vec<NovelsData>>,
+    #[serde(default)]
+    pub total: Option<u32>,
 }
 
 #[must_use]
@@ -310,3 +466,48 @@ pub(crate) struct NovelsResponse {
 pub(crate) struct NovelsData {
     pub novel_id: u32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn check_login() -> Result<(), Error> {
+        let response: LoginResponse = serde_json::from_str(
+            r#"{"status":{"httpCode":200,"errorCode":200,"msgType":0,"msg":null}}"#,
+        )?;
+        assert!(response.status.check_login().is_ok());
+
+        let response: LoginResponse = serde_json::from_str(
+            r#"{"status":{"httpCode":401,"errorCode":1001,"msgType":0,"msg":"用户名或密码错误"}}"#,
+        )?;
+        assert!(matches!(
+            response.status.check_login(),
+            Err(Error::InvalidCredentials(msg)) if msg == "用户名或密码错误"
+        ));
+
+        let response: LoginResponse = serde_json::from_str(
+            r#"{"status":{"httpCode":500,"errorCode":500,"msgType":0,"msg":"服务器错误"}}"#,
+        )?;
+        assert!(matches!(
+            response.status.check_login(),
+            Err(Error::Http { code, .. }) if code == StatusCode::INTERNAL_SERVER_ERROR
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn novels_dirs_chapter_info() -> Result<(), Error> {
+        let chapter: NovelsDirsChapterInfo = serde_json::from_str(
+            r#"{"chapId":1,"title":"第一章","isVip":false,"needFireMoney":0,"AddTime":"2020-07-08T15:25:15"}"#,
+        )?;
+        assert_eq!(chapter.char_count, None);
+        assert_eq!(chapter.is_vip, Some(false));
+        assert!(chapter.add_time.is_some());
+
+        Ok(())
+    }
+}
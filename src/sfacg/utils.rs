@@ -1,17 +1,28 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::{
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use boring::hash::{self, MessageDigest};
+use chrono::DateTime;
 use hex_simd::AsciiCase;
-use reqwest::Response;
+use reqwest::{header::DATE, Response};
 use serde::Serialize;
-use tokio::sync::OnceCell;
+use tokio::sync::{OnceCell, Semaphore};
+use tracing::{info, warn};
 use url::Url;
 use uuid::Uuid;
 
-use crate::{Error, HTTPClient, NovelDB, SfacgClient};
+use crate::{Error, HTTPClient, ImageLimits, LoginRetry, NovelDB, SfacgClient};
 
 impl SfacgClient {
-    const APP_NAME: &str = "sfacg";
+    pub(crate) const APP_NAME: &str = "sfacg";
+
+    /// Largest page size requested from sfacg, larger sizes are clamped
+    pub const MAX_PAGE_SIZE: u16 = 50;
+
+    /// Largest number of pages of a chapter that are downloaded, guards against a server that never stops paging
+    pub(crate) const MAX_CONTENT_PAGES: usize = 100;
 
     const HOST: &str = "https://api.sfacg.com";
     const USER_AGENT_PREFIX: &str = "boluobao/4.9.52(iOS;16.4.1)/appStore/";
@@ -26,14 +37,42 @@ impl SfacgClient {
     pub async fn new() -> Result<Self, Error> {
         Ok(Self {
             proxy: None,
+            image_proxy: None,
             no_proxy: false,
             cert_path: None,
+            pinned_cert: None,
+            min_tls_version: None,
+            pool_idle_timeout: None,
+            reqwest_client: None,
+            login_retry: LoginRetry::default(),
+            host: None,
+            stale_ok: false,
+            image_limits: ImageLimits::default(),
+            image_decodes: Semaphore::new(crate::default_image_decodes()),
+            write_behind_images: false,
+            no_cache: false,
+            image_cache_dir: None,
+            transport: None,
+            metrics: None,
             client: OnceCell::new(),
             client_rss: OnceCell::new(),
             db: OnceCell::new(),
+            time_offset: OnceCell::new(),
+            categories: OnceCell::new(),
+            tags: OnceCell::new(),
+            taxonomy: OnceCell::new(),
         })
     }
 
+    /// Wrap the client in an `Arc` so it can be shared across tasks
+    ///
+    /// All `Client` methods take `&self`, so the `Arc` can be cloned into spawned tasks,
+    /// configure the client before sharing it, the cookies are saved when the last handle is dropped
+    #[must_use]
+    pub fn into_shared(self) -> Arc<Self> {
+        Arc::new(self)
+    }
+
     #[inline]
     pub(crate) async fn client(&self) -> Result<&HTTPClient, Error> {
         self.client
@@ -49,6 +88,12 @@ impl SfacgClient {
                     .proxy(self.proxy.clone())
                     .no_proxy(self.no_proxy)
                     .cert(self.cert_path.clone())
+                    .pinned_cert(self.pinned_cert.clone())
+                    .min_tls_version(self.min_tls_version)
+                    .pool_idle_timeout(self.pool_idle_timeout)
+                    .reqwest_client(self.reqwest_client.clone())
+                    .transport(self.transport.clone())
+                    .metrics(self.metrics.clone())
                     .build()
                     .await
             })
@@ -59,13 +104,23 @@ impl SfacgClient {
     pub(crate) async fn client_rss(&self) -> Result<&HTTPClient, Error> {
         self.client_rss
             .get_or_try_init(|| async {
+                let (proxy, no_proxy) = match &self.image_proxy {
+                    Some(image_proxy) => (Some(image_proxy.clone()), false),
+                    None => (self.proxy.clone(), self.no_proxy),
+                };
+
                 HTTPClient::builder(SfacgClient::APP_NAME)
                     .accept("image/webp,image/*,*/*;q=0.8")
                     .accept_language("zh-CN,zh-Hans;q=0.9")
                     .user_agent(SfacgClient::USER_AGENT_RSS)
-                    .proxy(self.proxy.clone())
-                    .no_proxy(self.no_proxy)
+                    .proxy(proxy)
+                    .no_proxy(no_proxy)
                     .cert(self.cert_path.clone())
+                    .min_tls_version(self.min_tls_version)
+                    .pool_idle_timeout(self.pool_idle_timeout)
+                    .reqwest_client(self.reqwest_client.clone())
+                    .transport(self.transport.clone())
+                    .metrics(self.metrics.clone())
                     .build()
                     .await
             })
@@ -79,19 +134,37 @@ impl SfacgClient {
             .await
     }
 
+    /// Join the path to the host set by [`crate::Client::host`], or the production host
+    #[must_use]
+    fn api_url<T>(&self, path: T) -> String
+    where
+        T: AsRef<str>,
+    {
+        let host = match &self.host {
+            Some(host) => host.as_str().trim_end_matches('/'),
+            None => SfacgClient::HOST,
+        };
+
+        host.to_string() + path.as_ref()
+    }
+
     #[inline]
     pub(crate) async fn get<T>(&self, url: T) -> Result<Response, Error>
     where
         T: AsRef<str>,
     {
-        Ok(self
-            .client()
-            .await?
-            .get(SfacgClient::HOST.to_string() + url.as_ref())
-            .basic_auth(SfacgClient::USERNAME, Some(SfacgClient::PASSWORD))
-            .header("sfsecurity", self.sf_security()?)
-            .send()
-            .await?)
+        let client = self.client().await?;
+        let response = client
+            .send(
+                client
+                    .get(self.api_url(url.as_ref()))
+                    .basic_auth(SfacgClient::USERNAME, Some(SfacgClient::PASSWORD))
+                    .header("sfsecurity", self.sf_security()?),
+            )
+            .await?;
+        self.save_time_offset(&response);
+
+        Ok(response)
     }
 
     #[inline]
@@ -100,54 +173,198 @@ impl SfacgClient {
         T: AsRef<str>,
         E: Serialize,
     {
-        Ok(self
-            .client()
-            .await?
-            .get(SfacgClient::HOST.to_string() + url.as_ref())
-            .query(query)
-            .basic_auth(SfacgClient::USERNAME, Some(SfacgClient::PASSWORD))
-            .header("sfsecurity", self.sf_security()?)
-            .send()
-            .await?)
+        let client = self.client().await?;
+        let response = client
+            .send(
+                client
+                    .get(self.api_url(url.as_ref()))
+                    .query(query)
+                    .basic_auth(SfacgClient::USERNAME, Some(SfacgClient::PASSWORD))
+                    .header("sfsecurity", self.sf_security()?),
+            )
+            .await?;
+        self.save_time_offset(&response);
+
+        Ok(response)
     }
 
     #[inline]
     pub(crate) async fn get_rss(&self, url: &Url) -> Result<Response, Error> {
-        let response = self.client_rss().await?.get(url.clone()).send().await?;
-        crate::check_status(response.status(), format!("HTTP request failed: `{url}`"))?;
+        let client = self.client_rss().await?;
+        let response = client.send(client.get(url.clone())).await?;
+        crate::check_status(response, format!("HTTP request failed: `{url}`")).await
+    }
+
+    #[inline]
+    pub(crate) async fn post<T, E>(&self, url: T, json: &E) -> Result<Response, Error>
+    where
+        T: AsRef<str>,
+        E: Serialize,
+    {
+        let client = self.client().await?;
+        let response = client
+            .send(
+                client
+                    .post(self.api_url(url.as_ref()))
+                    .basic_auth(SfacgClient::USERNAME, Some(SfacgClient::PASSWORD))
+                    .header("sfsecurity", self.sf_security()?)
+                    .json(json),
+            )
+            .await?;
+        self.save_time_offset(&response);
 
         Ok(response)
     }
 
     #[inline]
-    pub(crate) async fn post<T, E>(&self, url: T, json: &E) -> Result<Response, Error>
+    pub(crate) async fn put<T, E>(&self, url: T, json: &E) -> Result<Response, Error>
     where
         T: AsRef<str>,
         E: Serialize,
     {
-        Ok(self
-            .client()
-            .await?
-            .post(SfacgClient::HOST.to_string() + url.as_ref())
-            .basic_auth(SfacgClient::USERNAME, Some(SfacgClient::PASSWORD))
-            .header("sfsecurity", self.sf_security()?)
-            .json(json)
-            .send()
-            .await?)
+        let client = self.client().await?;
+        let response = client
+            .send(
+                client
+                    .put(self.api_url(url.as_ref()))
+                    .basic_auth(SfacgClient::USERNAME, Some(SfacgClient::PASSWORD))
+                    .header("sfsecurity", self.sf_security()?)
+                    .json(json),
+            )
+            .await?;
+        self.save_time_offset(&response);
+
+        Ok(response)
     }
 
     #[inline]
     fn sf_security(&self) -> Result<String, Error> {
-        let uuid = Uuid::new_v4();
-        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
-        let device_token = crate::uid();
+        let timestamp = SfacgClient::corrected_timestamp(SystemTime::now(), self.time_offset())?;
 
-        let data = format!("{uuid}{timestamp}{device_token}{}", SfacgClient::SALT);
+        SfacgClient::sign(Uuid::new_v4(), timestamp, crate::uid())
+    }
+
+    /// Build the `sfsecurity` header from the nonce, the timestamp and the device token
+    fn sign(nonce: Uuid, timestamp: u64, device_token: &str) -> Result<String, Error> {
+        let data = format!("{nonce}{timestamp}{device_token}{}", SfacgClient::SALT);
         let md5 = hash::hash(MessageDigest::md5(), data.as_bytes())?;
 
         Ok(format!(
-            "nonce={uuid}&timestamp={timestamp}&devicetoken={device_token}&sign={}",
+            "nonce={nonce}&timestamp={timestamp}&devicetoken={device_token}&sign={}",
             hex_simd::encode_to_string(md5, AsciiCase::Upper)
         ))
     }
+
+    /// The difference in seconds between the server clock and the local clock,
+    /// 0 until the first response has been received
+    #[must_use]
+    pub(crate) fn time_offset(&self) -> i64 {
+        self.time_offset.get().copied().unwrap_or_default()
+    }
+
+    /// Take the server time from the `Date` header of the first response, so that no extra request is sent for it
+    fn save_time_offset(&self, response: &Response) {
+        if self.time_offset.initialized() {
+            return;
+        }
+
+        let offset = response
+            .headers()
+            .get(DATE)
+            .and_then(|date| date.to_str().ok())
+            .and_then(|date| SfacgClient::clock_offset(SystemTime::now(), date));
+
+        let offset = match offset {
+            Some(offset) => {
+                if offset != 0 {
+                    info!("The local clock differs from the server by {offset}s");
+                }
+                offset
+            }
+            None => {
+                warn!("Failed to get the server time, use the local clock");
+                0
+            }
+        };
+
+        // Another response may have set it first
+        let _ = self.time_offset.set(offset);
+    }
+
+    #[must_use]
+    fn clock_offset(local: SystemTime, date: &str) -> Option<i64> {
+        let server = DateTime::parse_from_rfc2822(date).ok()?.timestamp();
+        let local = local.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64;
+
+        Some(server - local)
+    }
+
+    fn corrected_timestamp(local: SystemTime, offset: i64) -> Result<u64, Error> {
+        let local = local.duration_since(UNIX_EPOCH)?.as_secs();
+
+        Ok(local.saturating_add_signed(offset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::Client;
+
+    #[test]
+    fn clock_offset() -> Result<(), Error> {
+        // 2001-09-09 01:46:40 UTC
+        let local = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+
+        let offset = SfacgClient::clock_offset(local, "Sun, 09 Sep 2001 01:48:20 GMT");
+        assert_eq!(offset, Some(100));
+        assert_eq!(
+            SfacgClient::corrected_timestamp(local, offset.unwrap())?,
+            1_000_000_100
+        );
+
+        let offset = SfacgClient::clock_offset(local, "Sun, 09 Sep 2001 01:45:00 GMT");
+        assert_eq!(offset, Some(-100));
+        assert_eq!(
+            SfacgClient::corrected_timestamp(local, offset.unwrap())?,
+            999_999_900
+        );
+
+        assert_eq!(SfacgClient::clock_offset(local, "invalid date"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn sign() -> Result<(), Error> {
+        assert_eq!(
+            SfacgClient::sign(Uuid::nil(), 1_000_000_000, "DEVICE-TOKEN")?,
+            "nonce=00000000-0000-0000-0000-000000000000&timestamp=1000000000\
+             &devicetoken=DEVICE-TOKEN&sign=9CB10B918F7282781C255F763F76D774"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn api_url() -> Result<(), Error> {
+        let mut client = SfacgClient::new().await?;
+        assert_eq!(
+            client.api_url("/novels/1"),
+            "https://api.sfacg.com/novels/1"
+        );
+
+        client.host(Url::parse("http://127.0.0.1:8080")?);
+        assert_eq!(
+            client.api_url("/novels/1"),
+            "http://127.0.0.1:8080/novels/1"
+        );
+
+        Ok(())
+    }
 }